@@ -0,0 +1,455 @@
+use anyhow::{anyhow, Error};
+use ciborium::value::Value;
+use evidence_api::tcg;
+use serde_json::json;
+
+/*
+ * Serializers of the TCG Canonical Event Log, used by cima-server for
+ * EVENTLOG_FORMAT_CEL_JSON and EVENTLOG_FORMAT_CEL_CBOR and by the SDK, so both
+ * produce the same bytes. The SDK decodes the records back with from_json and from_cbor.
+ */
+
+// TLV types of the TCG Canonical Event Log, also used as the keys of the CBOR records.
+pub const CEL_RECNUM: u64 = 0;
+pub const CEL_PCR: u64 = 1;
+pub const CEL_DIGESTS: u64 = 3;
+pub const CEL_PCCLIENT_STD: u64 = 5;
+pub const CEL_IMA_TEMPLATE: u64 = 7;
+
+// Keys of the fields in the CBOR record content.
+pub const CEL_EVENT_TYPE: u64 = 0;
+pub const CEL_EVENT_DATA: u64 = 1;
+pub const CEL_TEMPLATE_NAME: u64 = 0;
+pub const CEL_TEMPLATE_DATA: u64 = 1;
+
+pub const IMA_TEMPLATE_NAME: &str = "ima-cgpath";
+
+// An event of the event log, implemented by the TcgEventlog records of the server and the SDK.
+pub trait CelEvent {
+    fn imr_index(&self) -> u32;
+    fn event_type(&self) -> u32;
+    fn event_data(&self) -> &[u8];
+    // The algorithm ID and the hash of each digest.
+    fn digests(&self) -> Vec<(u32, &[u8])>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CelContent {
+    PcClientStd {
+        event_type: u32,
+        event_data: Vec<u8>,
+    },
+    ImaTemplate {
+        template_name: String,
+        template_data: Vec<u8>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CelRecord {
+    pub recnum: u64,
+    pub pcr: u32,
+    pub digests: Vec<(u32, Vec<u8>)>,
+    pub content: CelContent,
+}
+
+impl CelRecord {
+    // The record of an event, IMA events of the ima-cgpath template have the ima_template content.
+    pub fn new<E: CelEvent>(recnum: u64, event: &E) -> CelRecord {
+        let mut content = CelContent::PcClientStd {
+            event_type: event.event_type(),
+            event_data: event.event_data().to_vec(),
+        };
+        if event.event_type() == tcg::IMA_MEASUREMENT_EVENT {
+            if let Some(template_data) = ima_template_data(event.event_data()) {
+                content = CelContent::ImaTemplate {
+                    template_name: IMA_TEMPLATE_NAME.to_string(),
+                    template_data,
+                };
+            }
+        }
+
+        CelRecord {
+            recnum,
+            pcr: event.imr_index(),
+            digests: event
+                .digests()
+                .into_iter()
+                .map(|(algo_id, hash)| (algo_id, hash.to_vec()))
+                .collect(),
+            content,
+        }
+    }
+
+    // The event type of the record, IMA_MEASUREMENT_EVENT for the IMA template records.
+    pub fn event_type(&self) -> u32 {
+        match &self.content {
+            CelContent::PcClientStd { event_type, .. } => *event_type,
+            CelContent::ImaTemplate { .. } => tcg::IMA_MEASUREMENT_EVENT,
+        }
+    }
+
+    // The event data of the record, the IMA template data is turned back into the event text.
+    pub fn event_data(&self) -> Result<Vec<u8>, Error> {
+        match &self.content {
+            CelContent::PcClientStd { event_data, .. } => Ok(event_data.clone()),
+            CelContent::ImaTemplate {
+                template_name,
+                template_data,
+            } => {
+                if template_name != IMA_TEMPLATE_NAME {
+                    return Err(anyhow!("Unsupported IMA template {}.", template_name));
+                }
+                ima_event_data(template_data)
+                    .ok_or_else(|| anyhow!("Invalid {} template data.", IMA_TEMPLATE_NAME))
+            }
+        }
+    }
+}
+
+fn hash_alg_name(algo_id: u32) -> Result<&'static str, Error> {
+    let algo_id: u16 = algo_id.try_into()?;
+    match algo_id {
+        tcg::TPM_ALG_SHA1 => Ok("sha1"),
+        tcg::TPM_ALG_SHA256 => Ok("sha256"),
+        tcg::TPM_ALG_SHA384 => Ok("sha384"),
+        tcg::TPM_ALG_SHA512 => Ok("sha512"),
+        _ => Err(anyhow!("Unsupported hash algorithm {}.", algo_id)),
+    }
+}
+
+fn hash_alg_id(name: &str) -> Result<u32, Error> {
+    let algo_id = match name {
+        "sha1" => tcg::TPM_ALG_SHA1,
+        "sha256" => tcg::TPM_ALG_SHA256,
+        "sha384" => tcg::TPM_ALG_SHA384,
+        "sha512" => tcg::TPM_ALG_SHA512,
+        _ => return Err(anyhow!("Unsupported hash algorithm {}.", name)),
+    };
+    Ok(algo_id.into())
+}
+
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    data.as_bytes()
+        .chunks(2)
+        .map(|c| match c.len() {
+            2 => u8::from_str_radix(std::str::from_utf8(c).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+// Rebuild the binary template data of the ima-cgpath template "dep|cg-path|d-ng|n-ng"
+// from the event text, each field is prefixed with its length as u32 little endian.
+pub fn ima_template_data(event: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(event).ok()?;
+    let fields: Vec<&str> = text.splitn(4, ' ').collect();
+    if fields.len() != 4 {
+        return None;
+    }
+
+    let (algo, digest) = fields[2].split_once(':')?;
+    let digest = decode_hex(digest)?;
+
+    let dep = [fields[0].as_bytes(), &[0]].concat();
+    let cgpath = [fields[1].as_bytes(), &[0]].concat();
+    let d_ng = [algo.as_bytes(), b":\0", &digest].concat();
+    let n_ng = [fields[3].as_bytes(), &[0]].concat();
+
+    let mut template_data = vec![];
+    for field in [dep, cgpath, d_ng, n_ng] {
+        template_data.extend((field.len() as u32).to_le_bytes());
+        template_data.extend(field);
+    }
+
+    Some(template_data)
+}
+
+// The event text of ima-cgpath template data, the reverse of ima_template_data.
+pub fn ima_event_data(template_data: &[u8]) -> Option<Vec<u8>> {
+    let mut fields = vec![];
+    let mut data = template_data;
+    while !data.is_empty() {
+        let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        fields.push(data.get(4..4 + len)?);
+        data = &data[4 + len..];
+    }
+
+    let [dep, cgpath, d_ng, n_ng] = fields.as_slice() else {
+        return None;
+    };
+    let position = d_ng.windows(2).position(|w| w == b":\0")?;
+    let (algo, digest) = (&d_ng[..position], &d_ng[position + 2..]);
+    let digest: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let text = |field: &[u8]| -> Option<String> {
+        Some(
+            std::str::from_utf8(field.strip_suffix(&[0])?)
+                .ok()?
+                .to_string(),
+        )
+    };
+    let event = format!(
+        "{} {} {}:{} {}",
+        text(dep)?,
+        text(cgpath)?,
+        std::str::from_utf8(algo).ok()?,
+        digest,
+        text(n_ng)?
+    );
+    Some(event.into_bytes())
+}
+
+fn json_record(record: &CelRecord) -> Result<serde_json::Value, Error> {
+    let mut digests = vec![];
+    for (algo_id, hash) in &record.digests {
+        digests.push(json!({
+            "hashAlg": hash_alg_name(*algo_id)?,
+            "digest": hash.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        }));
+    }
+
+    let (content_type, content) = match &record.content {
+        CelContent::PcClientStd {
+            event_type,
+            event_data,
+        } => (
+            "pcclient_std",
+            json!({
+                "event_type": event_type,
+                "event_data": base64::encode(event_data),
+            }),
+        ),
+        CelContent::ImaTemplate {
+            template_name,
+            template_data,
+        } => (
+            "ima_template",
+            json!({
+                "template_name": template_name,
+                "template_data": base64::encode(template_data),
+            }),
+        ),
+    };
+
+    Ok(json!({
+        "recnum": record.recnum,
+        "pcr": record.pcr,
+        "digests": digests,
+        "content_type": content_type,
+        "content": content,
+    }))
+}
+
+fn cbor_record(record: &CelRecord) -> Value {
+    let digests = record
+        .digests
+        .iter()
+        .map(|(algo_id, hash)| (Value::from(*algo_id), Value::Bytes(hash.clone())))
+        .collect();
+
+    let (content_type, content) = match &record.content {
+        CelContent::PcClientStd {
+            event_type,
+            event_data,
+        } => (
+            CEL_PCCLIENT_STD,
+            vec![
+                (Value::from(CEL_EVENT_TYPE), Value::from(*event_type)),
+                (
+                    Value::from(CEL_EVENT_DATA),
+                    Value::Bytes(event_data.clone()),
+                ),
+            ],
+        ),
+        CelContent::ImaTemplate {
+            template_name,
+            template_data,
+        } => (
+            CEL_IMA_TEMPLATE,
+            vec![
+                (
+                    Value::from(CEL_TEMPLATE_NAME),
+                    Value::Text(template_name.clone()),
+                ),
+                (
+                    Value::from(CEL_TEMPLATE_DATA),
+                    Value::Bytes(template_data.clone()),
+                ),
+            ],
+        ),
+    };
+
+    Value::Map(vec![
+        (Value::from(CEL_RECNUM), Value::from(record.recnum)),
+        (Value::from(CEL_PCR), Value::from(record.pcr)),
+        (Value::from(CEL_DIGESTS), Value::Map(digests)),
+        (Value::from(content_type), Value::Map(content)),
+    ])
+}
+
+fn records<E: CelEvent>(event_logs: &[E], start: u32) -> Vec<CelRecord> {
+    event_logs
+        .iter()
+        .enumerate()
+        .map(|(i, event)| CelRecord::new(start as u64 + i as u64, event))
+        .collect()
+}
+
+// Encode the event logs as a CEL JSON array, the record numbers start from start.
+pub fn to_json<E: CelEvent>(event_logs: &[E], start: u32) -> Result<Vec<u8>, Error> {
+    let mut records_json = vec![];
+    for record in records(event_logs, start).iter() {
+        records_json.push(json_record(record)?);
+    }
+
+    Ok(serde_json::to_vec(&records_json)?)
+}
+
+// Encode the event logs as a CEL CBOR array, the record numbers start from start.
+pub fn to_cbor<E: CelEvent>(event_logs: &[E], start: u32) -> Result<Vec<u8>, Error> {
+    let records_cbor = records(event_logs, start).iter().map(cbor_record).collect();
+
+    let mut data = vec![];
+    match ciborium::ser::into_writer(&Value::Array(records_cbor), &mut data) {
+        Ok(_v) => Ok(data),
+        Err(e) => Err(anyhow!("Encode CEL CBOR failed: {:?}", e)),
+    }
+}
+
+fn json_field<'a>(value: &'a serde_json::Value, key: &str) -> Result<&'a serde_json::Value, Error> {
+    value
+        .get(key)
+        .ok_or_else(|| anyhow!("The CEL JSON record has no {}.", key))
+}
+
+fn json_str<'a>(value: &'a serde_json::Value, key: &str) -> Result<&'a str, Error> {
+    json_field(value, key)?
+        .as_str()
+        .ok_or_else(|| anyhow!("The CEL JSON field {} is not a string.", key))
+}
+
+fn json_u64(value: &serde_json::Value, key: &str) -> Result<u64, Error> {
+    json_field(value, key)?
+        .as_u64()
+        .ok_or_else(|| anyhow!("The CEL JSON field {} is not an integer.", key))
+}
+
+fn json_to_record(value: &serde_json::Value) -> Result<CelRecord, Error> {
+    let mut digests = vec![];
+    let json_digests = json_field(value, "digests")?
+        .as_array()
+        .ok_or_else(|| anyhow!("The CEL JSON digests are not an array."))?;
+    for d in json_digests {
+        let hash = decode_hex(json_str(d, "digest")?)
+            .ok_or_else(|| anyhow!("The CEL JSON digest is not hex encoded."))?;
+        digests.push((hash_alg_id(json_str(d, "hashAlg")?)?, hash));
+    }
+
+    let content = json_field(value, "content")?;
+    let content = match json_str(value, "content_type")? {
+        "pcclient_std" => CelContent::PcClientStd {
+            event_type: json_u64(content, "event_type")?.try_into()?,
+            event_data: base64::decode(json_str(content, "event_data")?)?,
+        },
+        "ima_template" => CelContent::ImaTemplate {
+            template_name: json_str(content, "template_name")?.to_string(),
+            template_data: base64::decode(json_str(content, "template_data")?)?,
+        },
+        v => return Err(anyhow!("Unsupported CEL content type {}.", v)),
+    };
+
+    Ok(CelRecord {
+        recnum: json_u64(value, "recnum")?,
+        pcr: json_u64(value, "pcr")?.try_into()?,
+        digests,
+        content,
+    })
+}
+
+// Decode a CEL JSON array, as encoded by to_json.
+pub fn from_json(data: &[u8]) -> Result<Vec<CelRecord>, Error> {
+    let records: Vec<serde_json::Value> = serde_json::from_slice(data)?;
+    records.iter().map(json_to_record).collect()
+}
+
+fn cbor_u64(value: &Value) -> Result<u64, Error> {
+    value
+        .as_integer()
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or_else(|| anyhow!("The CEL CBOR item is not an unsigned integer."))
+}
+
+fn cbor_bytes(value: &Value) -> Result<Vec<u8>, Error> {
+    value
+        .as_bytes()
+        .cloned()
+        .ok_or_else(|| anyhow!("The CEL CBOR item is not a byte string."))
+}
+
+// The entries of a CBOR map with integer keys.
+fn cbor_map(value: &Value) -> Result<Vec<(u64, &Value)>, Error> {
+    let map = value
+        .as_map()
+        .ok_or_else(|| anyhow!("The CEL CBOR item is not a map."))?;
+    let mut entries = vec![];
+    for (k, v) in map {
+        entries.push((cbor_u64(k)?, v));
+    }
+    Ok(entries)
+}
+
+fn cbor_field<'a>(map: &[(u64, &'a Value)], key: u64) -> Option<&'a Value> {
+    map.iter().find(|(k, _v)| *k == key).map(|(_k, v)| *v)
+}
+
+fn cbor_required<'a>(map: &[(u64, &'a Value)], key: u64) -> Result<&'a Value, Error> {
+    cbor_field(map, key).ok_or_else(|| anyhow!("The CEL CBOR record has no key {}.", key))
+}
+
+fn cbor_to_record(value: &Value) -> Result<CelRecord, Error> {
+    let map = cbor_map(value)?;
+
+    let mut digests = vec![];
+    for (algo_id, hash) in cbor_map(cbor_required(&map, CEL_DIGESTS)?)? {
+        digests.push((algo_id.try_into()?, cbor_bytes(hash)?));
+    }
+
+    let content = if let Some(v) = cbor_field(&map, CEL_PCCLIENT_STD) {
+        let v = cbor_map(v)?;
+        CelContent::PcClientStd {
+            event_type: cbor_u64(cbor_required(&v, CEL_EVENT_TYPE)?)?.try_into()?,
+            event_data: cbor_bytes(cbor_required(&v, CEL_EVENT_DATA)?)?,
+        }
+    } else if let Some(v) = cbor_field(&map, CEL_IMA_TEMPLATE) {
+        let v = cbor_map(v)?;
+        let template_name = cbor_required(&v, CEL_TEMPLATE_NAME)?
+            .as_text()
+            .ok_or_else(|| anyhow!("The CEL CBOR template name is not a text."))?;
+        CelContent::ImaTemplate {
+            template_name: template_name.to_string(),
+            template_data: cbor_bytes(cbor_required(&v, CEL_TEMPLATE_DATA)?)?,
+        }
+    } else {
+        return Err(anyhow!("Unsupported CEL CBOR record content."));
+    };
+
+    Ok(CelRecord {
+        recnum: cbor_u64(cbor_required(&map, CEL_RECNUM)?)?,
+        pcr: cbor_u64(cbor_required(&map, CEL_PCR)?)?.try_into()?,
+        digests,
+        content,
+    })
+}
+
+// Decode a CEL CBOR array, as encoded by to_cbor.
+pub fn from_cbor(data: &[u8]) -> Result<Vec<CelRecord>, Error> {
+    let value: Value = match ciborium::de::from_reader(data) {
+        Ok(v) => v,
+        Err(e) => return Err(anyhow!("Decode CEL CBOR failed: {:?}", e)),
+    };
+    let records = value
+        .as_array()
+        .ok_or_else(|| anyhow!("The CEL CBOR is not an array."))?;
+    records.iter().map(cbor_to_record).collect()
+}
@@ -0,0 +1,224 @@
+use std::io;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{unix::AsyncFd, AsyncRead, AsyncWrite, ReadBuf};
+
+/*
+ * AF_VSOCK stream sockets driven by the tokio reactor, the listener of cima-server and
+ * the connections of both the server and the SDK.
+ */
+
+fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret)
+}
+
+fn socket() -> io::Result<OwnedFd> {
+    let fd = check(unsafe {
+        libc::socket(
+            libc::AF_VSOCK,
+            libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            0,
+        )
+    })?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn socket_addr(cid: u32, port: u32) -> libc::sockaddr_vm {
+    libc::sockaddr_vm {
+        svm_family: libc::AF_VSOCK as libc::sa_family_t,
+        svm_reserved1: 0,
+        svm_port: port,
+        svm_cid: cid,
+        svm_zero: [0; 4],
+    }
+}
+
+pub struct VsockListener {
+    inner: AsyncFd<OwnedFd>,
+}
+
+impl VsockListener {
+    // Listen on the port for any CID.
+    pub fn bind(port: u32) -> io::Result<VsockListener> {
+        let fd = socket()?;
+        let addr = socket_addr(libc::VMADDR_CID_ANY, port);
+        check(unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_vm as *const libc::sockaddr,
+                size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+            )
+        })?;
+        check(unsafe { libc::listen(fd.as_raw_fd(), libc::SOMAXCONN) })?;
+
+        Ok(VsockListener {
+            inner: AsyncFd::new(fd)?,
+        })
+    }
+
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<VsockStream>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            let accepted = guard.try_io(|fd| {
+                let mut addr = socket_addr(0, 0);
+                let mut len = size_of::<libc::sockaddr_vm>() as libc::socklen_t;
+                let fd = check(unsafe {
+                    libc::accept4(
+                        fd.as_raw_fd(),
+                        &mut addr as *mut libc::sockaddr_vm as *mut libc::sockaddr,
+                        &mut len,
+                        libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                    )
+                })?;
+                Ok((unsafe { OwnedFd::from_raw_fd(fd) }, addr))
+            });
+
+            match accepted {
+                Ok(Ok((fd, addr))) => {
+                    return Poll::Ready(Ok(VsockStream {
+                        inner: AsyncFd::new(fd)?,
+                        peer_cid: addr.svm_cid,
+                        peer_port: addr.svm_port,
+                    }))
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+pub struct VsockStream {
+    inner: AsyncFd<OwnedFd>,
+    peer_cid: u32,
+    peer_port: u32,
+}
+
+impl VsockStream {
+    pub async fn connect(cid: u32, port: u32) -> io::Result<VsockStream> {
+        let fd = socket()?;
+        let addr = socket_addr(cid, port);
+        let ret = unsafe {
+            libc::connect(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_vm as *const libc::sockaddr,
+                size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::EINPROGRESS) {
+                return Err(e);
+            }
+        }
+
+        // The socket is writable once the connection completes, SO_ERROR tells the result.
+        let inner = AsyncFd::new(fd)?;
+        let _guard = inner.writable().await?;
+        let mut err: libc::c_int = 0;
+        let mut len = size_of::<libc::c_int>() as libc::socklen_t;
+        check(unsafe {
+            libc::getsockopt(
+                inner.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_ERROR,
+                &mut err as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            )
+        })?;
+        if err != 0 {
+            return Err(io::Error::from_raw_os_error(err));
+        }
+
+        Ok(VsockStream {
+            inner,
+            peer_cid: cid,
+            peer_port: port,
+        })
+    }
+
+    // The CID and the port of the other end of the connection.
+    pub fn peer_addr(&self) -> (u32, u32) {
+        (self.peer_cid, self.peer_port)
+    }
+}
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            let read = guard.try_io(|fd| {
+                let n = unsafe {
+                    libc::read(
+                        fd.as_raw_fd(),
+                        unfilled.as_mut_ptr() as *mut libc::c_void,
+                        unfilled.len(),
+                    )
+                };
+                if n < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(n as usize)
+            });
+
+            match read {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_write_ready(cx))?;
+            let written = guard.try_io(|fd| {
+                let n = unsafe {
+                    libc::send(
+                        fd.as_raw_fd(),
+                        buf.as_ptr() as *const libc::c_void,
+                        buf.len(),
+                        libc::MSG_NOSIGNAL,
+                    )
+                };
+                if n < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(n as usize)
+            });
+
+            match written {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        check(unsafe { libc::shutdown(self.inner.as_raw_fd(), libc::SHUT_WR) })?;
+        Poll::Ready(Ok(()))
+    }
+}
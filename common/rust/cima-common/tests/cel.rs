@@ -0,0 +1,144 @@
+/*
+ * The CEL encodings against golden records, which verifiers of cima-server output
+ * already decode. data/cel.json and data/cel.cbor are the records from 5 of events().
+ */
+use cima_common::cel::{self, CelContent, CelEvent, CelRecord, IMA_TEMPLATE_NAME};
+use evidence_api::tcg::{
+    EV_NO_ACTION, IMA_MEASUREMENT_EVENT, TPM_ALG_SHA1, TPM_ALG_SHA256, TPM_ALG_SHA384,
+    TPM_ALG_SHA512,
+};
+
+const GOLDEN_JSON: &[u8] = include_bytes!("data/cel.json");
+const GOLDEN_CBOR: &[u8] = include_bytes!("data/cel.cbor");
+
+struct Event {
+    imr_index: u32,
+    event_type: u32,
+    event: Vec<u8>,
+    digests: Vec<(u32, Vec<u8>)>,
+}
+
+impl CelEvent for Event {
+    fn imr_index(&self) -> u32 {
+        self.imr_index
+    }
+
+    fn event_type(&self) -> u32 {
+        self.event_type
+    }
+
+    fn event_data(&self) -> &[u8] {
+        &self.event
+    }
+
+    fn digests(&self) -> Vec<(u32, &[u8])> {
+        self.digests
+            .iter()
+            .map(|(algo_id, hash)| (*algo_id, hash.as_slice()))
+            .collect()
+    }
+}
+
+fn event(imr_index: u32, event_type: u32, event: &[u8], digests: &[(u16, usize)]) -> Event {
+    Event {
+        imr_index,
+        event_type,
+        event: event.to_vec(),
+        digests: digests
+            .iter()
+            .map(|(algo_id, size)| ((*algo_id).into(), vec![*size as u8; *size]))
+            .collect(),
+    }
+}
+
+fn events() -> Vec<Event> {
+    let ima = format!(
+        "{} {} sha256:{} /usr/bin/bash",
+        "/usr/lib/systemd/systemd",
+        "/kubepods.slice/kubepods-pod1.slice/cri-containerd-abc.scope",
+        "ab".repeat(32)
+    );
+    vec![
+        event(0, EV_NO_ACTION, b"Spec ID Event03\0", &[(TPM_ALG_SHA1, 20)]),
+        event(
+            1,
+            0x80000001,
+            &[1, 2, 3],
+            &[(TPM_ALG_SHA256, 32), (TPM_ALG_SHA384, 48)],
+        ),
+        event(
+            3,
+            IMA_MEASUREMENT_EVENT,
+            ima.as_bytes(),
+            &[(TPM_ALG_SHA384, 48)],
+        ),
+        // Not an ima-cgpath record, it is kept as a PC Client event.
+        event(
+            3,
+            IMA_MEASUREMENT_EVENT,
+            b"boot_aggregate",
+            &[(TPM_ALG_SHA512, 64)],
+        ),
+    ]
+}
+
+#[test]
+fn cel_golden_encodings() {
+    let events = events();
+    assert_eq!(cel::to_json(&events, 5).unwrap(), GOLDEN_JSON);
+    assert_eq!(cel::to_cbor(&events, 5).unwrap(), GOLDEN_CBOR);
+}
+
+#[test]
+fn cel_records() {
+    let events = events();
+    let record = CelRecord::new(7, &events[2]);
+    assert_eq!(record.pcr, 3);
+    assert_eq!(record.digests, vec![(TPM_ALG_SHA384.into(), vec![48; 48])]);
+    match record.content {
+        CelContent::ImaTemplate {
+            template_name,
+            template_data,
+        } => {
+            assert_eq!(template_name, IMA_TEMPLATE_NAME);
+            assert_eq!(
+                Some(template_data),
+                cel::ima_template_data(&events[2].event)
+            );
+        }
+        _ => panic!("Excepted an IMA template record"),
+    }
+
+    assert!(matches!(
+        CelRecord::new(8, &events[3]).content,
+        CelContent::PcClientStd { event_type, .. } if event_type == IMA_MEASUREMENT_EVENT
+    ));
+    assert_eq!(cel::ima_template_data(b"boot_aggregate"), None);
+}
+
+#[test]
+fn cel_golden_decodings() {
+    let events = events();
+    let expected: Vec<CelRecord> = events
+        .iter()
+        .enumerate()
+        .map(|(i, event)| CelRecord::new(5 + i as u64, event))
+        .collect();
+    let records = cel::from_json(GOLDEN_JSON).unwrap();
+    assert_eq!(records, expected);
+    assert_eq!(cel::from_cbor(GOLDEN_CBOR).unwrap(), expected);
+
+    for (record, event) in records.iter().zip(events.iter()) {
+        assert_eq!(record.event_type(), event.event_type);
+        assert_eq!(record.event_data().unwrap(), event.event);
+    }
+
+    assert!(cel::from_json(b"[{\"recnum\":0}]").is_err());
+    assert!(cel::from_cbor(&GOLDEN_CBOR[..GOLDEN_CBOR.len() - 1]).is_err());
+}
+
+#[test]
+fn cel_unsupported_algorithm() {
+    let events = vec![event(0, EV_NO_ACTION, &[], &[(0x12, 32)])];
+    assert!(cel::to_json(&events, 0).is_err());
+}
@@ -0,0 +1,59 @@
+/*
+ * Latency of the calls to a running cima-server: a new runtime and channel for each call,
+ * as the SDK used to do, against the shared runtime and the reused channel.
+ *
+ * Run it next to the server, the socket is given by CIMA_SOCK:
+ *     cargo bench --bench client
+ */
+use cima::client::{CimaAsyncClient, CimaServiceClient};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+fn show(name: &str, elapsed: Duration) {
+    println!("{:<36} {:>12.1?} per call", name, elapsed / ITERATIONS);
+}
+
+fn main() {
+    let mut client = CimaServiceClient::builder().build();
+    let sock = client.address().to_string();
+    if let Err(e) = client.get_cc_default_algorithm_from_server() {
+        println!("No cima-server on {}, skipped: {}", sock, e);
+        return;
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                CimaAsyncClient::connect(&sock)
+                    .await?
+                    .get_default_algorithm()
+                    .await
+            })
+            .unwrap();
+    }
+    show("new runtime and channel per call", start.elapsed());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        client.get_cc_default_algorithm_from_server().unwrap();
+    }
+    show("sync client, shared runtime", start.elapsed());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let client = CimaAsyncClient::connect(&sock).await.unwrap();
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            client.get_default_algorithm().await.unwrap();
+        }
+        show("async client", start.elapsed());
+    });
+}
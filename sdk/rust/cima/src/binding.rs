@@ -0,0 +1,77 @@
+use crate::client::cima_server_pb::ReportBinding;
+use anyhow::anyhow;
+use openssl::hash::{hash, Hasher, MessageDigest};
+
+/*
+ * Verifier side of the report binding scheme v1 of cima-server, the layout of the
+ * binding is described in service/cima-server/src/binding.rs.
+ */
+pub const BINDING_VERSION: u32 = 1;
+const BINDING_TAG: &[u8] = b"CIMA-REPORT-BINDING-V1";
+
+fn update_with_len(hasher: &mut Hasher, data: &[u8]) -> Result<(), anyhow::Error> {
+    hasher.update(&(data.len() as u32).to_le_bytes())?;
+    hasher.update(data)?;
+    Ok(())
+}
+
+fn decode_base64(value: Option<String>) -> Result<Vec<u8>, anyhow::Error> {
+    match value {
+        Some(v) => match base64::decode(v) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(anyhow!(
+                "[decode_base64] value is not base64 encoded: {:?}",
+                e
+            )),
+        },
+        None => Ok(vec![]),
+    }
+}
+
+/*
+ * The report data the server asks the backend for, recomputed from the binding returned
+ * by the server and the nonce/user_data sent to it. It is the same for every TEE, the
+ * report of the backend carries it the way the TEE does for a nonce with no user data.
+ */
+pub fn expected_report_data(
+    binding: &ReportBinding,
+    nonce: Option<String>,
+    user_data: Option<String>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    if binding.version != BINDING_VERSION {
+        return Err(anyhow!(
+            "[expected_report_data] unsupported binding version: {}",
+            binding.version
+        ));
+    }
+
+    let imr = match &binding.container_imr {
+        Some(imr) => imr,
+        None => return Err(anyhow!("[expected_report_data] container IMR is missing")),
+    };
+
+    let mut hasher = Hasher::new(MessageDigest::sha384())?;
+    hasher.update(BINDING_TAG)?;
+    update_with_len(&mut hasher, binding.container_id.as_bytes())?;
+    hasher.update(&(imr.algo_id as u16).to_le_bytes())?;
+    update_with_len(&mut hasher, &imr.hash)?;
+    hasher.update(&binding.event_count.to_le_bytes())?;
+    update_with_len(&mut hasher, &decode_base64(nonce)?)?;
+    update_with_len(&mut hasher, &decode_base64(user_data)?)?;
+
+    Ok(hasher.finish()?.to_vec())
+}
+
+// check the REPORTDATA of a TDX quote against the binding, TDX signs SHA512 of the report data
+pub fn verify_tdx_report_data(
+    binding: &ReportBinding,
+    nonce: Option<String>,
+    user_data: Option<String>,
+    report_data: &[u8],
+) -> Result<bool, anyhow::Error> {
+    let expected = hash(
+        MessageDigest::sha512(),
+        &expected_report_data(binding, nonce, user_data)?,
+    )?;
+    Ok(expected.as_ref() == report_data)
+}
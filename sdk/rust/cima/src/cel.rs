@@ -0,0 +1,28 @@
+use crate::client::cima_server_pb::TcgEventlog;
+
+/*
+ * Serializers of the TCG Canonical Event Log, shared with cima-server, so they produce
+ * the same encodings as it returns for EVENTLOG_FORMAT_CEL_JSON and EVENTLOG_FORMAT_CEL_CBOR.
+ */
+pub use cima_common::cel::*;
+
+impl CelEvent for TcgEventlog {
+    fn imr_index(&self) -> u32 {
+        self.imr_index
+    }
+
+    fn event_type(&self) -> u32 {
+        self.event_type
+    }
+
+    fn event_data(&self) -> &[u8] {
+        &self.event
+    }
+
+    fn digests(&self) -> Vec<(u32, &[u8])> {
+        self.digests
+            .iter()
+            .map(|d| (d.algo_id, d.hash.as_slice()))
+            .collect()
+    }
+}
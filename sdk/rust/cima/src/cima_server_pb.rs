@@ -91,6 +91,22 @@ pub struct GetCcReportResponse {
     pub cc_type: i32,
     #[prost(bytes = "vec", tag = "2")]
     pub cc_report: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "3")]
+    pub unfiltered: bool,
+    #[prost(message, optional, tag = "4")]
+    pub binding: ::core::option::Option<ReportBinding>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReportBinding {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub container_imr: ::core::option::Option<TcgDigest>,
+    #[prost(uint32, tag = "4")]
+    pub event_count: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -107,6 +123,8 @@ pub struct GetCcMeasurementRequest {
 pub struct GetCcMeasurementResponse {
     #[prost(message, optional, tag = "1")]
     pub measurement: ::core::option::Option<TcgDigest>,
+    #[prost(bool, tag = "2")]
+    pub unfiltered: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -117,6 +135,8 @@ pub struct GetCcEventlogRequest {
     pub start: ::core::option::Option<u32>,
     #[prost(uint32, optional, tag = "3")]
     pub count: ::core::option::Option<u32>,
+    #[prost(enumeration = "EventlogFormat", tag = "4")]
+    pub format: i32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -152,6 +172,200 @@ pub struct TcgEventlog {
 pub struct GetCcEventlogResponse {
     #[prost(message, repeated, tag = "1")]
     pub event_logs: ::prost::alloc::vec::Vec<TcgEventlog>,
+    #[prost(bool, tag = "2")]
+    pub unfiltered: bool,
+    #[prost(bytes = "vec", tag = "3")]
+    pub cel_event_logs: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "4")]
+    pub ima_ascii_event_logs: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetIsolationModeRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetIsolationModeResponse {
+    #[prost(enumeration = "IsolationMode", tag = "1")]
+    pub mode: i32,
+    #[prost(bool, tag = "2")]
+    pub ima_enabled: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetServerInfoRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetServerInfoResponse {
+    #[prost(string, tag = "1")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub backend: ::prost::alloc::string::String,
+    /// The TEE type reported by the backend.
+    #[prost(int32, tag = "3")]
+    pub cc_type: i32,
+    #[prost(bool, tag = "4")]
+    pub ima_enabled: bool,
+    #[prost(bool, tag = "5")]
+    pub container_isolated: bool,
+    #[prost(enumeration = "IsolationMode", tag = "6")]
+    pub isolation_mode: i32,
+    /// The default algorithm of the backend, then the other algorithms of the event log digests.
+    #[prost(uint32, repeated, tag = "7")]
+    pub algo_ids: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, repeated, tag = "8")]
+    pub imr_indexes: ::prost::alloc::vec::Vec<u32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetEvidenceRequest {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub user_data: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub nonce: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImrMeasurement {
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(message, optional, tag = "2")]
+    pub measurement: ::core::option::Option<TcgDigest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetEvidenceResponse {
+    #[prost(int32, tag = "1")]
+    pub cc_type: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub cc_report: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, repeated, tag = "3")]
+    pub measurements: ::prost::alloc::vec::Vec<ImrMeasurement>,
+    #[prost(message, repeated, tag = "4")]
+    pub event_logs: ::prost::alloc::vec::Vec<TcgEventlog>,
+    #[prost(bool, tag = "5")]
+    pub unfiltered: bool,
+    #[prost(message, optional, tag = "6")]
+    pub binding: ::core::option::Option<ReportBinding>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExtendContainerMeasurementRequest {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub event: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExtendContainerMeasurementResponse {
+    #[prost(message, optional, tag = "1")]
+    pub measurement: ::core::option::Option<TcgDigest>,
+    #[prost(uint32, tag = "2")]
+    pub event_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetReadinessRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadinessCheck {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub ok: bool,
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetReadinessResponse {
+    #[prost(bool, tag = "1")]
+    pub ready: bool,
+    #[prost(message, repeated, tag = "2")]
+    pub checks: ::prost::alloc::vec::Vec<ReadinessCheck>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum IsolationMode {
+    Unspecified = 0,
+    /// IMA is enabled, each container only sees its own measurements.
+    Isolated = 1,
+    /// Container isolation is disabled by the policy.
+    Shared = 2,
+    /// IMA is not enabled, container evidence requests are refused.
+    Refuse = 3,
+    /// IMA is not enabled, only firmware and kernel measurements are served.
+    FirmwareOnly = 4,
+    /// IMA is not enabled, the full event log is served to every container.
+    Unfiltered = 5,
+}
+impl IsolationMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            IsolationMode::Unspecified => "ISOLATION_MODE_UNSPECIFIED",
+            IsolationMode::Isolated => "ISOLATION_MODE_ISOLATED",
+            IsolationMode::Shared => "ISOLATION_MODE_SHARED",
+            IsolationMode::Refuse => "ISOLATION_MODE_REFUSE",
+            IsolationMode::FirmwareOnly => "ISOLATION_MODE_FIRMWARE_ONLY",
+            IsolationMode::Unfiltered => "ISOLATION_MODE_UNFILTERED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ISOLATION_MODE_UNSPECIFIED" => Some(Self::Unspecified),
+            "ISOLATION_MODE_ISOLATED" => Some(Self::Isolated),
+            "ISOLATION_MODE_SHARED" => Some(Self::Shared),
+            "ISOLATION_MODE_REFUSE" => Some(Self::Refuse),
+            "ISOLATION_MODE_FIRMWARE_ONLY" => Some(Self::FirmwareOnly),
+            "ISOLATION_MODE_UNFILTERED" => Some(Self::Unfiltered),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum EventlogFormat {
+    /// TcgEventlog records in event_logs.
+    Tcg = 0,
+    /// TCG Canonical Event Log JSON encoding in cel_event_logs.
+    CelJson = 1,
+    /// TCG Canonical Event Log CBOR encoding in cel_event_logs.
+    CelCbor = 2,
+    /// IMA ascii_runtime_measurements text in ima_ascii_event_logs.
+    ImaAscii = 3,
+}
+impl EventlogFormat {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            EventlogFormat::Tcg => "EVENTLOG_FORMAT_TCG",
+            EventlogFormat::CelJson => "EVENTLOG_FORMAT_CEL_JSON",
+            EventlogFormat::CelCbor => "EVENTLOG_FORMAT_CEL_CBOR",
+            EventlogFormat::ImaAscii => "EVENTLOG_FORMAT_IMA_ASCII",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "EVENTLOG_FORMAT_TCG" => Some(Self::Tcg),
+            "EVENTLOG_FORMAT_CEL_JSON" => Some(Self::CelJson),
+            "EVENTLOG_FORMAT_CEL_CBOR" => Some(Self::CelCbor),
+            "EVENTLOG_FORMAT_IMA_ASCII" => Some(Self::ImaAscii),
+            _ => None,
+        }
+    }
 }
 /// Generated client implementations.
 pub mod cima_client {
@@ -363,6 +577,133 @@ pub mod cima_client {
                 .insert(GrpcMethod::new("cima_server_pb.cima", "GetCcEventlog"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn get_isolation_mode(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetIsolationModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetIsolationModeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cima_server_pb.cima/GetIsolationMode",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cima_server_pb.cima", "GetIsolationMode"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_server_info(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetServerInfoRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetServerInfoResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cima_server_pb.cima/GetServerInfo",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cima_server_pb.cima", "GetServerInfo"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_evidence(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetEvidenceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetEvidenceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cima_server_pb.cima/GetEvidence",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cima_server_pb.cima", "GetEvidence"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn extend_container_measurement(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExtendContainerMeasurementRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExtendContainerMeasurementResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cima_server_pb.cima/ExtendContainerMeasurement",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("cima_server_pb.cima", "ExtendContainerMeasurement"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_readiness(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetReadinessRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetReadinessResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cima_server_pb.cima/GetReadiness",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cima_server_pb.cima", "GetReadiness"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -407,6 +748,41 @@ pub mod cima_server {
             tonic::Response<super::GetCcEventlogResponse>,
             tonic::Status,
         >;
+        async fn get_isolation_mode(
+            &self,
+            request: tonic::Request<super::GetIsolationModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetIsolationModeResponse>,
+            tonic::Status,
+        >;
+        async fn get_server_info(
+            &self,
+            request: tonic::Request<super::GetServerInfoRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetServerInfoResponse>,
+            tonic::Status,
+        >;
+        async fn get_evidence(
+            &self,
+            request: tonic::Request<super::GetEvidenceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetEvidenceResponse>,
+            tonic::Status,
+        >;
+        async fn extend_container_measurement(
+            &self,
+            request: tonic::Request<super::ExtendContainerMeasurementRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExtendContainerMeasurementResponse>,
+            tonic::Status,
+        >;
+        async fn get_readiness(
+            &self,
+            request: tonic::Request<super::GetReadinessRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetReadinessResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct CimaServer<T: Cima> {
@@ -715,6 +1091,236 @@ pub mod cima_server {
                     };
                     Box::pin(fut)
                 }
+                "/cima_server_pb.cima/GetIsolationMode" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetIsolationModeSvc<T: Cima>(pub Arc<T>);
+                    impl<
+                        T: Cima,
+                    > tonic::server::UnaryService<super::GetIsolationModeRequest>
+                    for GetIsolationModeSvc<T> {
+                        type Response = super::GetIsolationModeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetIsolationModeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Cima>::get_isolation_mode(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetIsolationModeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cima_server_pb.cima/GetServerInfo" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetServerInfoSvc<T: Cima>(pub Arc<T>);
+                    impl<
+                        T: Cima,
+                    > tonic::server::UnaryService<super::GetServerInfoRequest>
+                    for GetServerInfoSvc<T> {
+                        type Response = super::GetServerInfoResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetServerInfoRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Cima>::get_server_info(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetServerInfoSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cima_server_pb.cima/GetEvidence" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetEvidenceSvc<T: Cima>(pub Arc<T>);
+                    impl<T: Cima> tonic::server::UnaryService<super::GetEvidenceRequest>
+                    for GetEvidenceSvc<T> {
+                        type Response = super::GetEvidenceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetEvidenceRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Cima>::get_evidence(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetEvidenceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cima_server_pb.cima/ExtendContainerMeasurement" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExtendContainerMeasurementSvc<T: Cima>(pub Arc<T>);
+                    impl<
+                        T: Cima,
+                    > tonic::server::UnaryService<
+                        super::ExtendContainerMeasurementRequest,
+                    > for ExtendContainerMeasurementSvc<T> {
+                        type Response = super::ExtendContainerMeasurementResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                super::ExtendContainerMeasurementRequest,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Cima>::extend_container_measurement(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExtendContainerMeasurementSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cima_server_pb.cima/GetReadiness" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetReadinessSvc<T: Cima>(pub Arc<T>);
+                    impl<T: Cima> tonic::server::UnaryService<super::GetReadinessRequest>
+                    for GetReadinessSvc<T> {
+                        type Response = super::GetReadinessResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetReadinessRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Cima>::get_readiness(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetReadinessSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -1,19 +1,35 @@
 use crate::client::cima_server_pb::{
-    cima_client::CimaClient, GetCcEventlogRequest, GetCcEventlogResponse, GetCcMeasurementRequest,
-    GetCcMeasurementResponse, GetCcReportRequest, GetCcReportResponse, GetDefaultAlgorithmRequest,
-    GetDefaultAlgorithmResponse, GetMeasurementCountRequest, GetMeasurementCountResponse,
+    cima_client::CimaClient, EventlogFormat, GetCcEventlogRequest, GetCcEventlogResponse,
+    GetCcMeasurementRequest, GetCcMeasurementResponse, GetCcReportRequest, GetCcReportResponse,
+    GetDefaultAlgorithmRequest, GetDefaultAlgorithmResponse, GetMeasurementCountRequest,
+    GetMeasurementCountResponse, GetReadinessRequest, GetReadinessResponse, GetServerInfoRequest,
+    GetServerInfoResponse,
 };
-use anyhow::anyhow;
+use core::result::Result::Ok;
 use evidence_api::api_data::ExtraArgs;
 use evidence_api::cc_type::TeeType;
-use core::result::Result::Ok;
 use hashbrown::HashMap;
-use std::fs::read_to_string;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::UnixStream;
-use tonic::transport::{Endpoint, Uri};
-use tonic::Request;
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::OnceCell;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::{Code, Request, Response, Status};
 use tower::service_fn;
 
+use crate::container;
+use crate::error::CimaError;
+use crate::replay::{self, ReplayReport};
+use crate::vsock::{self, VsockStream};
+
+pub const DEFAULT_SOCK: &str = "/run/cima/uds/cima-server.sock";
+// Environment variable overriding the default server address.
+pub const SOCK_ENV: &str = "CIMA_SOCK";
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(100);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
 lazy_static! {
     pub static ref TEE_VALUE_TYPE_MAP: HashMap<i32, TeeType> = {
         let mut map: HashMap<i32, TeeType> = HashMap::new();
@@ -24,172 +40,364 @@ lazy_static! {
         map.insert(3, TeeType::CCA);
         map
     };
+
+    // The runtime of the sync calls, it is shared so the channels outlive a single call.
+    pub(crate) static ref RUNTIME: Runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("cima-sdk")
+        .enable_all()
+        .build()
+        .expect("Failed to create the runtime of the CIMA SDK.");
 }
 
 pub mod cima_server_pb {
     tonic::include_proto!("cima_server_pb");
 }
 
-pub struct CimaServiceClient {
-    pub cima_uds_path: String,
+/*
+ * Wait for a future on the shared runtime. A thread of the caller's runtime must not
+ * block on another runtime, so the future is then waited for on a scoped thread.
+ */
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    if Handle::try_current().is_err() {
+        return RUNTIME.block_on(future);
+    }
+    std::thread::scope(|s| s.spawn(|| RUNTIME.block_on(future)).join())
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))
 }
 
-impl CimaServiceClient {
-    async fn get_cc_report_from_server_async(
-        &mut self,
-        nonce: Option<String>,
-        data: Option<String>,
-        _extra_args: ExtraArgs,
-    ) -> Result<GetCcReportResponse, anyhow::Error> {
-        let uds_path = self.cima_uds_path.parse::<Uri>().unwrap();
-        let channel = Endpoint::try_from("http://[::]:0")
-            .unwrap()
-            .connect_with_connector(service_fn(move |_: Uri| {
-                UnixStream::connect(uds_path.to_string())
-            }))
-            .await
-            .unwrap();
-
-        let container_id = match self.get_container_id() {
-            Ok(id) => id,
-            Err(e) => {
-                return Err(anyhow!(
-                    "[get_cc_report_from_server_async] error getting the container ID: {:?}",
-                    e
-                ));
-            }
-        };
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_BACKOFF)
+}
 
-        let request = Request::new(GetCcReportRequest {
-            container_id,
-            nonce,
-            user_data: data,
-        });
+/*
+ * Options of the connection to the CIMA server. The address is taken from CIMA_SOCK, or
+ * is the default socket path. No timeout is set and the calls are not retried by default.
+ */
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    address: String,
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+    container_id: Option<String>,
+}
 
-        let mut cima_client = CimaClient::new(channel).max_decoding_message_size(usize::MAX);
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        let response = cima_client
-            .get_cc_report(request)
-            .await
-            .unwrap()
-            .into_inner();
-        Ok(response)
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        ClientBuilder {
+            address: std::env::var(SOCK_ENV).unwrap_or_else(|_| DEFAULT_SOCK.to_string()),
+            connect_timeout: None,
+            request_timeout: None,
+            retries: 0,
+            backoff: DEFAULT_BACKOFF,
+            container_id: None,
+        }
     }
 
-    // turn async call to sync call
-    pub fn get_cc_report_from_server(
-        &mut self,
-        nonce: Option<String>,
-        data: Option<String>,
-        extra_args: ExtraArgs,
-    ) -> Result<GetCcReportResponse, anyhow::Error> {
-        let response = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(self.get_cc_report_from_server_async(nonce, data, extra_args));
-        response
+    // address is either the path of the unix domain socket or a "vsock://<cid>:<port>"
+    // address of a cima-server serving vsock.
+    pub fn address(mut self, address: &str) -> ClientBuilder {
+        self.address = address.to_string();
+        self
     }
 
-    pub fn get_tee_type_by_value(&self, tee_id: &i32) -> TeeType {
-        match TEE_VALUE_TYPE_MAP.get(tee_id) {
-            Some(tee_type) => tee_type.clone(),
-            None => TeeType::PLAIN,
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    // Retry the connection and the calls failing as unavailable, up to `retries` times.
+    // The first retry waits `backoff`, each next one twice as long, up to MAX_BACKOFF.
+    pub fn retry(mut self, retries: u32, backoff: Duration) -> ClientBuilder {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    // The container ID sent to the server, instead of the one discovered.
+    pub fn container_id(mut self, container_id: &str) -> ClientBuilder {
+        self.container_id = Some(container_id.to_string());
+        self
+    }
+
+    fn get_container_id(&self) -> Result<String, CimaError> {
+        match &self.container_id {
+            Some(v) => Ok(v.clone()),
+            None => container::get_container_id(),
         }
     }
 
-    async fn get_cc_measurement_from_server_async(
-        &mut self,
-        index: u8,
-        algo_id: u16,
-    ) -> Result<GetCcMeasurementResponse, anyhow::Error> {
-        let uds_path = self.cima_uds_path.parse::<Uri>().unwrap();
-        let channel = Endpoint::try_from("http://[::]:0")
-            .unwrap()
-            .connect_with_connector(service_fn(move |_: Uri| {
-                UnixStream::connect(uds_path.to_string())
-            }))
-            .await
-            .unwrap();
-
-        let container_id = match self.get_container_id() {
-            Ok(id) => id,
-            Err(e) => {
-                return Err(anyhow!(
-                    "[get_cc_measurement_from_server_async] error getting the container ID: {:?}",
-                    e
-                ));
+    async fn connect_channel(&self) -> Result<Channel, CimaError> {
+        let mut endpoint = Endpoint::from_static("http://[::]:0");
+        if let Some(v) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(v);
+        }
+        if let Some(v) = self.request_timeout {
+            endpoint = endpoint.timeout(v);
+        }
+
+        let channel = match vsock::parse_address(&self.address) {
+            Some(v) => {
+                let (cid, port) = v.map_err(|e| CimaError::InvalidArgument(e.to_string()))?;
+                endpoint
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        VsockStream::connect(cid, port)
+                    }))
+                    .await?
+            }
+            None => {
+                let uds_path = self.address.clone();
+                endpoint
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        UnixStream::connect(uds_path.clone())
+                    }))
+                    .await?
             }
         };
+        Ok(channel)
+    }
 
-        let request = Request::new(GetCcMeasurementRequest {
-            container_id,
-            index: index.into(),
-            algo_id: algo_id.into(),
-        });
+    pub async fn connect(&self) -> Result<CimaAsyncClient, CimaError> {
+        let mut backoff = self.backoff;
+        let mut retries = 0;
+        let channel = loop {
+            match self.connect_channel().await {
+                Ok(v) => break v,
+                Err(e) if e.retryable() && retries < self.retries => {
+                    log::debug!("[connect] retry in {:?}: {:?}", backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = next_backoff(backoff);
+                    retries += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        Ok(CimaAsyncClient {
+            options: self.clone(),
+            client: CimaClient::new(channel).max_decoding_message_size(usize::MAX),
+        })
+    }
+
+    // The sync client, it connects on its first call.
+    pub fn build(self) -> CimaServiceClient {
+        CimaServiceClient {
+            options: self,
+            client: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+/*
+ * Async client of the CIMA server. It holds one channel, which all the calls share, and
+ * clones of the client share it too.
+ */
+#[derive(Clone)]
+pub struct CimaAsyncClient {
+    options: ClientBuilder,
+    client: CimaClient<Channel>,
+}
+
+impl CimaAsyncClient {
+    pub async fn connect(address: &str) -> Result<CimaAsyncClient, CimaError> {
+        ClientBuilder::new().address(address).connect().await
+    }
+
+    // vsock callers are outside of the VM and can only query the system evidence,
+    // so no container ID is sent for them.
+    fn request_container_id(&self) -> Result<String, CimaError> {
+        if vsock::parse_address(&self.options.address).is_some() {
+            return Ok(String::new());
+        }
+        self.options.get_container_id()
+    }
+
+    // Send the message, again with backoff as long as the server is unavailable.
+    async fn call<T, R, F, Fut>(&self, message: T, rpc: F) -> Result<R, CimaError>
+    where
+        T: Clone,
+        F: Fn(CimaClient<Channel>, Request<T>) -> Fut,
+        Fut: Future<Output = Result<Response<R>, Status>>,
+    {
+        let mut backoff = self.options.backoff;
+        let mut retries = 0;
+        loop {
+            match rpc(self.client.clone(), Request::new(message.clone())).await {
+                Ok(v) => return Ok(v.into_inner()),
+                Err(e) if e.code() == Code::Unavailable && retries < self.options.retries => {
+                    log::debug!("[call] retry in {:?}: {:?}", backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = next_backoff(backoff);
+                    retries += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 
-        let mut cima_client = CimaClient::new(channel).max_decoding_message_size(usize::MAX);
+    pub async fn get_cc_report(
+        &self,
+        nonce: Option<String>,
+        data: Option<String>,
+    ) -> Result<GetCcReportResponse, CimaError> {
+        let container_id = self.request_container_id()?;
 
-        let response = cima_client
-            .get_cc_measurement(request)
+        let message = GetCcReportRequest {
+            container_id,
+            nonce,
+            user_data: data,
+        };
+        self.call(message, |mut c, r| async move { c.get_cc_report(r).await })
             .await
-            .unwrap()
-            .into_inner();
-        Ok(response)
     }
 
-    // turn async call to sync call
-    pub fn get_cc_measurement_from_server(
-        &mut self,
+    pub async fn get_cc_measurement(
+        &self,
         index: u8,
         algo_id: u16,
-    ) -> Result<GetCcMeasurementResponse, anyhow::Error> {
-        let response = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(self.get_cc_measurement_from_server_async(index, algo_id));
-        response
+    ) -> Result<GetCcMeasurementResponse, CimaError> {
+        let container_id = self.request_container_id()?;
+
+        let message = GetCcMeasurementRequest {
+            container_id,
+            index: index.into(),
+            algo_id: algo_id.into(),
+        };
+        self.call(
+            message,
+            |mut c, r| async move { c.get_cc_measurement(r).await },
+        )
+        .await
     }
 
-    async fn get_cc_eventlog_from_server_async(
-        &mut self,
+    pub async fn get_cc_eventlog(
+        &self,
         start: Option<u32>,
         count: Option<u32>,
-    ) -> Result<GetCcEventlogResponse, anyhow::Error> {
-        let uds_path = self.cima_uds_path.parse::<Uri>().unwrap();
-        let channel = Endpoint::try_from("http://[::]:0")
-            .unwrap()
-            .connect_with_connector(service_fn(move |_: Uri| {
-                UnixStream::connect(uds_path.to_string())
-            }))
-            .await
-            .unwrap();
-
-        let container_id = match self.get_container_id() {
-            Ok(id) => id,
-            Err(e) => {
-                return Err(anyhow!(
-                    "[get_cc_eventlog_from_server_async] error getting the container ID: {:?}",
-                    e
-                ));
-            }
-        };
+        format: EventlogFormat,
+    ) -> Result<GetCcEventlogResponse, CimaError> {
+        let container_id = self.request_container_id()?;
 
-        let request = Request::new(GetCcEventlogRequest {
+        let message = GetCcEventlogRequest {
             container_id,
             start,
             count,
-        });
+            format: format.into(),
+        };
+        self.call(
+            message,
+            |mut c, r| async move { c.get_cc_eventlog(r).await },
+        )
+        .await
+    }
 
-        let mut cima_client = CimaClient::new(channel).max_decoding_message_size(usize::MAX);
+    pub async fn get_measurement_count(&self) -> Result<GetMeasurementCountResponse, CimaError> {
+        self.call(GetMeasurementCountRequest {}, |mut c, r| async move {
+            c.get_measurement_count(r).await
+        })
+        .await
+    }
 
-        let response = cima_client
-            .get_cc_eventlog(request)
-            .await
-            .unwrap()
-            .into_inner();
-        Ok(response)
+    pub async fn get_default_algorithm(&self) -> Result<GetDefaultAlgorithmResponse, CimaError> {
+        self.call(GetDefaultAlgorithmRequest {}, |mut c, r| async move {
+            c.get_default_algorithm(r).await
+        })
+        .await
+    }
+
+    pub async fn get_server_info(&self) -> Result<GetServerInfoResponse, CimaError> {
+        self.call(GetServerInfoRequest {}, |mut c, r| async move {
+            c.get_server_info(r).await
+        })
+        .await
+    }
+
+    pub async fn get_readiness(&self) -> Result<GetReadinessResponse, CimaError> {
+        self.call(GetReadinessRequest {}, |mut c, r| async move {
+            c.get_readiness(r).await
+        })
+        .await
+    }
+}
+
+/*
+ * Sync client of the CIMA server. The calls run on a runtime shared by the process, and
+ * share one channel with the clones of the client.
+ */
+#[derive(Clone)]
+pub struct CimaServiceClient {
+    options: ClientBuilder,
+    client: Arc<OnceCell<CimaAsyncClient>>,
+}
+
+impl CimaServiceClient {
+    pub fn new(address: &str) -> CimaServiceClient {
+        ClientBuilder::new().address(address).build()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub fn address(&self) -> &str {
+        &self.options.address
+    }
+
+    // The async client, connected by the first call.
+    async fn client(&self) -> Result<CimaAsyncClient, CimaError> {
+        let client = self
+            .client
+            .get_or_try_init(|| self.options.connect())
+            .await?;
+        Ok(client.clone())
+    }
+
+    // turn async call to sync call
+    pub fn get_cc_report_from_server(
+        &mut self,
+        nonce: Option<String>,
+        data: Option<String>,
+        _extra_args: ExtraArgs,
+    ) -> Result<GetCcReportResponse, CimaError> {
+        block_on(async { self.client().await?.get_cc_report(nonce, data).await })
+    }
+
+    pub fn get_tee_type_by_value(&self, tee_id: &i32) -> TeeType {
+        match TEE_VALUE_TYPE_MAP.get(tee_id) {
+            Some(tee_type) => tee_type.clone(),
+            None => TeeType::PLAIN,
+        }
+    }
+
+    // turn async call to sync call
+    pub fn get_cc_measurement_from_server(
+        &mut self,
+        index: u8,
+        algo_id: u16,
+    ) -> Result<GetCcMeasurementResponse, CimaError> {
+        block_on(async {
+            self.client()
+                .await?
+                .get_cc_measurement(index, algo_id)
+                .await
+        })
     }
 
     // turn async call to sync call
@@ -197,134 +405,84 @@ impl CimaServiceClient {
         &mut self,
         start: Option<u32>,
         count: Option<u32>,
-    ) -> Result<GetCcEventlogResponse, anyhow::Error> {
-        let response = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(self.get_cc_eventlog_from_server_async(start, count));
-        response
+    ) -> Result<GetCcEventlogResponse, CimaError> {
+        block_on(async {
+            self.client()
+                .await?
+                .get_cc_eventlog(start, count, EventlogFormat::Tcg)
+                .await
+        })
     }
 
-    async fn get_cc_measurement_count_from_server_async(
+    // turn async call to sync call, the event logs are returned in the TCG CEL encoding
+    pub fn get_cc_eventlog_cel_from_server(
         &mut self,
-    ) -> Result<GetMeasurementCountResponse, anyhow::Error> {
-        let uds_path = self.cima_uds_path.parse::<Uri>().unwrap();
-        let channel = Endpoint::try_from("http://[::]:0")
-            .unwrap()
-            .connect_with_connector(service_fn(move |_: Uri| {
-                UnixStream::connect(uds_path.to_string())
-            }))
-            .await
-            .unwrap();
-
-        let request = Request::new(GetMeasurementCountRequest {});
+        start: Option<u32>,
+        count: Option<u32>,
+        format: EventlogFormat,
+    ) -> Result<Vec<u8>, CimaError> {
+        if format != EventlogFormat::CelJson && format != EventlogFormat::CelCbor {
+            return Err(CimaError::InvalidArgument(
+                "[get_cc_eventlog_cel_from_server] format must be a CEL encoding".to_string(),
+            ));
+        }
 
-        let mut cima_client = CimaClient::new(channel);
+        let response = block_on(async {
+            self.client()
+                .await?
+                .get_cc_eventlog(start, count, format)
+                .await
+        })?;
+        Ok(response.cel_event_logs)
+    }
 
-        let response = cima_client
-            .get_measurement_count(request)
-            .await
-            .unwrap()
-            .into_inner();
-        Ok(response)
+    // turn async call to sync call, the IMA events of the container are returned in the
+    // ascii_runtime_measurements format
+    pub fn get_cc_eventlog_ima_ascii_from_server(
+        &mut self,
+        start: Option<u32>,
+        count: Option<u32>,
+    ) -> Result<String, CimaError> {
+        let response = block_on(async {
+            self.client()
+                .await?
+                .get_cc_eventlog(start, count, EventlogFormat::ImaAscii)
+                .await
+        })?;
+        Ok(response.ima_ascii_event_logs)
     }
 
     // turn async call to sync call
     pub fn get_cc_measurement_count_from_server(
         &mut self,
-    ) -> Result<GetMeasurementCountResponse, anyhow::Error> {
-        let response = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(self.get_cc_measurement_count_from_server_async());
-        response
+    ) -> Result<GetMeasurementCountResponse, CimaError> {
+        block_on(async { self.client().await?.get_measurement_count().await })
     }
 
-    async fn get_cc_default_algorithm_from_server_async(
+    // turn async call to sync call
+    pub fn get_cc_default_algorithm_from_server(
         &mut self,
-    ) -> Result<GetDefaultAlgorithmResponse, anyhow::Error> {
-        let uds_path = self.cima_uds_path.parse::<Uri>().unwrap();
-        let channel = Endpoint::try_from("http://[::]:0")
-            .unwrap()
-            .connect_with_connector(service_fn(move |_: Uri| {
-                UnixStream::connect(uds_path.to_string())
-            }))
-            .await
-            .unwrap();
-
-        let request = Request::new(GetDefaultAlgorithmRequest {});
-
-        let mut cima_client = CimaClient::new(channel);
+    ) -> Result<GetDefaultAlgorithmResponse, CimaError> {
+        block_on(async { self.client().await?.get_default_algorithm().await })
+    }
 
-        let response = cima_client
-            .get_default_algorithm(request)
-            .await
-            .unwrap()
-            .into_inner();
-        Ok(response)
+    // turn async call to sync call
+    pub fn get_server_info_from_server(&mut self) -> Result<GetServerInfoResponse, CimaError> {
+        block_on(async { self.client().await?.get_server_info().await })
     }
 
     // turn async call to sync call
-    pub fn get_cc_default_algorithm_from_server(
-        &mut self,
-    ) -> Result<GetDefaultAlgorithmResponse, anyhow::Error> {
-        let response = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .unwrap()
-            .block_on(self.get_cc_default_algorithm_from_server_async());
-        response
-    }
-
-    pub fn get_container_id(&self) -> Result<String, anyhow::Error> {
-        let mountinfo = "/proc/self/mountinfo".to_string();
-        let docker_pattern = "/docker/containers/";
-        let k8s_pattern = "/kubelet/pods/";
-
-        let data_lines: Vec<String> = read_to_string(mountinfo)
-            .unwrap()
-            .lines()
-            .map(String::from)
-            .collect();
-
-        for line in data_lines {
-            /*
-             * line format:
-             *      ... /var/lib/docker/containers/{container-id}/{file} ...
-             * sample:
-             */
-            if line.contains(docker_pattern) {
-                let element = line.split(docker_pattern).last();
-                if element.is_some() {
-                    let (id, _) = element.unwrap().split_once('/').unwrap();
-                    return Ok(id.to_string());
-                } else {
-                    return Err(anyhow!("[get_container_id] incorrect docker container info in /proc/self/mountinfo!"));
-                }
-            }
+    pub fn get_readiness_from_server(&mut self) -> Result<GetReadinessResponse, CimaError> {
+        block_on(async { self.client().await?.get_readiness().await })
+    }
 
-            /*
-             * line format:
-             *      ... /var/lib/kubelet/pods/{container-id}/{file} ...
-             * sample:
-             *      2958 2938 253:1 /var/lib/kubelet/pods/a45f46f0-20be-45ab-ace6-b77e8e2f062c/containers/busybox/8f8d892c /dev/termination-log rw,relatime - ext4 /dev/vda1 rw,discard,errors=remount-ro
-             */
-            if line.contains(k8s_pattern) {
-                let element = line.split(k8s_pattern).last();
-                if element.is_some() {
-                    let (left, _) = element.unwrap().split_once('/').unwrap();
-                    let id = left.replace('-', "_");
-                    return Ok(id);
-                } else {
-                    return Err(anyhow!("[get_container_id] incorrect k8s pod container info in /proc/self/mountinfo!"));
-                }
-            }
-        }
+    // turn async call to sync call, the event logs of the caller are replayed against the
+    // measurements of its IMRs
+    pub fn verify_event_logs_from_server(&mut self) -> Result<ReplayReport, anyhow::Error> {
+        block_on(async { replay::verify(&self.client().await?).await })
+    }
 
-        Err(anyhow!(
-            "[get_container_id] no container info in /proc/self/mountinfo!"
-        ))
+    pub fn get_container_id(&self) -> Result<String, CimaError> {
+        self.options.get_container_id()
     }
 }
@@ -0,0 +1,117 @@
+use crate::error::CimaError;
+use regex::Regex;
+use std::fs::read_to_string;
+
+// Environment variable giving the container ID, which skips the discovery.
+pub const CONTAINER_ID_ENV: &str = "CIMA_CONTAINER_ID";
+
+const CGROUP: &str = "/proc/self/cgroup";
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/*
+ * Parse the container ID from a cgroup path, the same way as Container::parse_id of
+ * cima-server: the pod UID of a Kubernetes container, with '_' in place of '-', or the
+ * ID of a Docker container scope. The server measures no other cgroup as a container,
+ * so no other path gives an ID.
+ */
+pub fn parse_id_from_path(cgpath: &str) -> Option<String> {
+    if !cgpath.contains("kubepods.slice") && !cgpath.contains("system.slice/docker-") {
+        return None;
+    }
+    let path = match cgpath.find("kubepods.slice") {
+        Some(v) => &cgpath[v..],
+        None => &cgpath[cgpath.find("system.slice")?..],
+    };
+
+    let re = Regex::new(r"[[:xdigit:]_]{36}.slice|[[:xdigit:]]{32}.slice|[[:xdigit:]]{64}.scope")
+        .ok()?;
+    let id = re.find(path)?.as_str();
+    Some(id.replace(".slice", "").replace(".scope", ""))
+}
+
+/*
+ * line format:
+ *      cgroup v1: {hierarchy-id}:{controllers}:{path}
+ *      cgroup v2: 0::{path}
+ * sample:
+ *      0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod8c0cbb8f_3f4a_4b56_9b8f_1d1f0e0a3f42.slice/cri-containerd-5e5f....scope
+ *
+ * The path is "/" in a private cgroup namespace, which gives no ID.
+ */
+pub fn id_from_cgroup(cgroup: &str) -> Option<String> {
+    cgroup
+        .lines()
+        .filter_map(|l| l.splitn(3, ':').nth(2))
+        .find_map(parse_id_from_path)
+}
+
+fn id_after<'a>(line: &'a str, pattern: &str) -> Option<&'a str> {
+    let (_, element) = line.rsplit_once(pattern)?;
+    let (id, _) = element.split_once('/')?;
+    Some(id)
+}
+
+fn find_id(mountinfo: &str, pattern: &str) -> Option<String> {
+    mountinfo
+        .lines()
+        .filter_map(|l| id_after(l, pattern))
+        .find(|id| id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(String::from)
+}
+
+/*
+ * line format:
+ *      ... /var/lib/kubelet/pods/{pod-uid}/{file} ...
+ *      ... /var/lib/docker/containers/{container-id}/{file} ...
+ * sample:
+ *      2958 2938 253:1 /var/lib/kubelet/pods/a45f46f0-20be-45ab-ace6-b77e8e2f062c/containers/busybox/8f8d892c /dev/termination-log rw,relatime - ext4 /dev/vda1 rw,discard,errors=remount-ro
+ *
+ * The kubelet always mounts /etc/hosts of a pod, so the pod UID is looked for first.
+ */
+pub fn id_from_mountinfo(mountinfo: &str) -> Option<String> {
+    let pod = mountinfo
+        .lines()
+        .filter_map(|l| id_after(l, "/kubelet/pods/"))
+        .find(|uid| uid.len() == 36 && uid.chars().all(|c| c.is_ascii_hexdigit() || c == '-'))
+        .map(|uid| uid.replace('-', "_"));
+
+    pod.or_else(|| find_id(mountinfo, "/docker/containers/"))
+}
+
+/*
+ * The ID of the container of the current process, as cima-server identifies it. It is
+ * taken from CIMA_CONTAINER_ID, or from /proc/self/cgroup, or from /proc/self/mountinfo
+ * when the cgroup path is hidden by a cgroup namespace.
+ */
+pub fn get_container_id() -> Result<String, CimaError> {
+    if let Ok(v) = std::env::var(CONTAINER_ID_ENV) {
+        if !v.is_empty() {
+            return Ok(v);
+        }
+    }
+
+    if let Some(v) = read_to_string(CGROUP)
+        .ok()
+        .as_deref()
+        .and_then(id_from_cgroup)
+    {
+        return Ok(v);
+    }
+
+    let mountinfo = match read_to_string(MOUNTINFO) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(CimaError::NotInContainer(format!(
+                "[get_container_id] error reading {}: {}",
+                MOUNTINFO, e
+            )))
+        }
+    };
+    match id_from_mountinfo(&mountinfo) {
+        Some(v) => Ok(v),
+        None => Err(CimaError::NotInContainer(format!(
+            "[get_container_id] no container info in {} or {}",
+            CGROUP, MOUNTINFO
+        ))),
+    }
+}
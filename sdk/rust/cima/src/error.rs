@@ -0,0 +1,86 @@
+use std::fmt;
+use std::io;
+use tonic::{Code, Status};
+
+// Errors of the SDK client, so that applications can tell why attestation failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CimaError {
+    // The server cannot be connected, went away or did not answer in time.
+    ServerUnavailable(String),
+    // The container of the caller cannot be found.
+    NotInContainer(String),
+    // The server refuses the caller.
+    PermissionDenied(String),
+    // Any other failure reported by the server.
+    Server { code: Code, message: String },
+    // The call is not valid, e.g. a malformed server address.
+    InvalidArgument(String),
+}
+
+impl fmt::Display for CimaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CimaError::ServerUnavailable(v) => write!(f, "CIMA server unavailable: {}", v),
+            CimaError::NotInContainer(v) => write!(f, "not in a container: {}", v),
+            CimaError::PermissionDenied(v) => write!(f, "permission denied: {}", v),
+            CimaError::Server { code, message } => {
+                write!(f, "CIMA server error {:?}: {}", code, message)
+            }
+            CimaError::InvalidArgument(v) => write!(f, "invalid argument: {}", v),
+        }
+    }
+}
+
+impl std::error::Error for CimaError {}
+
+impl From<Status> for CimaError {
+    fn from(status: Status) -> Self {
+        let message = status.message().to_string();
+        match status.code() {
+            // A timeout of the client is reported as cancelled.
+            Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled => {
+                CimaError::ServerUnavailable(message)
+            }
+            Code::PermissionDenied | Code::Unauthenticated => CimaError::PermissionDenied(message),
+            code => CimaError::Server { code, message },
+        }
+    }
+}
+
+impl CimaError {
+    // Whether the call can succeed when done again later.
+    pub fn retryable(&self) -> bool {
+        matches!(self, CimaError::ServerUnavailable(_))
+    }
+}
+
+impl From<io::Error> for CimaError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => CimaError::PermissionDenied(error.to_string()),
+            _ => CimaError::ServerUnavailable(error.to_string()),
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for CimaError {
+    fn from(error: tonic::transport::Error) -> Self {
+        // The source tells why, e.g. the socket does not exist.
+        let message = match std::error::Error::source(&error) {
+            Some(v) => format!("{}: {}", error, v),
+            None => error.to_string(),
+        };
+
+        // The socket cannot be accessed when the caller lacks the permissions.
+        let mut source = std::error::Error::source(&error);
+        while let Some(v) = source {
+            if let Some(e) = v.downcast_ref::<io::Error>() {
+                if e.kind() == io::ErrorKind::PermissionDenied {
+                    return CimaError::PermissionDenied(message);
+                }
+            }
+            source = v.source();
+        }
+        CimaError::ServerUnavailable(message)
+    }
+}
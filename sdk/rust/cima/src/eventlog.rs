@@ -0,0 +1,123 @@
+use crate::cel::{self, CelRecord};
+use crate::client::cima_server_pb::{self, EventlogFormat, TcgEventlog};
+use anyhow::{anyhow, Error};
+use evidence_api::tcg::{
+    EventLogEntry, TcgCanonicalEvent, TcgDigest, TcgImrEvent, TcgPcClientImrEvent,
+};
+use std::collections::HashMap;
+
+// The key of extra_info, set by cima-server, telling the format of the event.
+pub const EVENT_FORMAT_KEY: &str = "format";
+// The event has the single SHA-1 digest of the TCG PC Client format.
+pub const PCCLIENT_FORMAT: &str = "pcclient";
+pub const SHA1_DIGEST_SIZE: usize = 20;
+
+/*
+ * Convert a record of the server into an event log entry. The events of the TCG PC
+ * Client format have their single SHA-1 digest, all the others, including the IMA and
+ * application events exported as CEL, keep the digests of all the algorithms.
+ */
+pub fn to_event_log_entry(event: TcgEventlog) -> Result<EventLogEntry, Error> {
+    if event.digests.is_empty() {
+        return Err(anyhow!(
+            "[to_event_log_entry] event {} has no digest",
+            event.rec_num
+        ));
+    }
+
+    if event.extra_info.get(EVENT_FORMAT_KEY).map(String::as_str) == Some(PCCLIENT_FORMAT) {
+        let digest: [u8; SHA1_DIGEST_SIZE] = match event.digests.as_slice() {
+            [d] if d.hash.len() == SHA1_DIGEST_SIZE => d.hash.as_slice().try_into()?,
+            _ => {
+                return Err(anyhow!(
+                    "[to_event_log_entry] PC Client event {} must have one SHA-1 digest",
+                    event.rec_num
+                ))
+            }
+        };
+        return Ok(EventLogEntry::TcgPcClientImrEvent(TcgPcClientImrEvent {
+            imr_index: event.imr_index,
+            event_type: event.event_type,
+            digest,
+            event_size: event.event_size,
+            event: event.event,
+        }));
+    }
+
+    let mut digests: Vec<TcgDigest> = Vec::new();
+    for d in event.digests {
+        let algo_id = match u16::try_from(d.algo_id) {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(anyhow!(
+                    "[to_event_log_entry] invalid algorithm {} of event {}",
+                    d.algo_id,
+                    event.rec_num
+                ))
+            }
+        };
+        digests.push(TcgDigest {
+            algo_id,
+            hash: d.hash,
+        });
+    }
+
+    Ok(EventLogEntry::TcgImrEvent(TcgImrEvent {
+        imr_index: event.imr_index,
+        event_type: event.event_type,
+        digests,
+        event_size: event.event_size,
+        event: event.event,
+    }))
+}
+
+// Decode the CEL records returned by the server for EventlogFormat::CelJson or CelCbor.
+pub fn decode_cel(data: &[u8], format: EventlogFormat) -> Result<Vec<CelRecord>, Error> {
+    match format {
+        EventlogFormat::CelJson => cel::from_json(data),
+        EventlogFormat::CelCbor => cel::from_cbor(data),
+        _ => Err(anyhow!(
+            "[decode_cel] format {:?} is not a CEL encoding",
+            format
+        )),
+    }
+}
+
+/*
+ * A CEL record is a canonical event. The TcgCanonicalEvent of evidence_api has no field
+ * yet, use cel_to_tcg_eventlog to keep the digests and the event of the record.
+ */
+pub fn cel_to_event_log_entry(_record: &CelRecord) -> EventLogEntry {
+    EventLogEntry::TcgCanonicalEvent(TcgCanonicalEvent {})
+}
+
+// Convert a CEL record back into the record the server returns for EventlogFormat::Tcg.
+pub fn cel_to_tcg_eventlog(record: &CelRecord) -> Result<TcgEventlog, Error> {
+    let rec_num = match u32::try_from(record.recnum) {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(anyhow!(
+                "[cel_to_tcg_eventlog] invalid record number {}",
+                record.recnum
+            ))
+        }
+    };
+    let event = record.event_data()?;
+
+    Ok(TcgEventlog {
+        rec_num,
+        imr_index: record.pcr,
+        event_type: record.event_type(),
+        event_size: event.len() as u32,
+        event,
+        digests: record
+            .digests
+            .iter()
+            .map(|(algo_id, hash)| cima_server_pb::TcgDigest {
+                algo_id: *algo_id,
+                hash: hash.clone(),
+            })
+            .collect(),
+        extra_info: HashMap::new(),
+    })
+}
@@ -1,5 +1,14 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod binding;
+pub mod cel;
 pub mod client;
+pub mod container;
+pub mod error;
+pub mod eventlog;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod replay;
 pub mod sdk;
+pub mod vsock;
@@ -0,0 +1,524 @@
+use crate::client::cima_server_pb::{
+    cima_server::{Cima, CimaServer},
+    EventlogFormat, ExtendContainerMeasurementRequest, ExtendContainerMeasurementResponse,
+    GetCcEventlogRequest, GetCcEventlogResponse, GetCcMeasurementRequest, GetCcMeasurementResponse,
+    GetCcReportRequest, GetCcReportResponse, GetDefaultAlgorithmRequest,
+    GetDefaultAlgorithmResponse, GetEvidenceRequest, GetEvidenceResponse, GetIsolationModeRequest,
+    GetIsolationModeResponse, GetMeasurementCountRequest, GetMeasurementCountResponse,
+    GetReadinessRequest, GetReadinessResponse, GetServerInfoRequest, GetServerInfoResponse,
+    ImrMeasurement, IsolationMode, TcgDigest, TcgEventlog,
+};
+use crate::client::{ClientBuilder, RUNTIME};
+use crate::{cel, replay};
+use anyhow::Error;
+use evidence_api::tcg;
+use openssl::hash::hash;
+use std::collections::BTreeMap;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::net::UnixListener;
+use tokio::sync::oneshot;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::{transport::Server, Code, Request, Response, Status};
+use tower::{layer::layer_fn, ServiceExt};
+
+// The container ID the clients of MockServer::client send.
+pub const MOCK_CONTAINER_ID: &str = "cima-mock";
+
+static MOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// A request received by the mock server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockRequest {
+    pub method: &'static str,
+    pub container_id: String,
+    pub nonce: Option<String>,
+    pub user_data: Option<String>,
+}
+
+/*
+ * The evidence served by the mock server. By default it is a TDX guest with SHA384 IMRs
+ * 0 to 3 all zero, no event log, an empty report and isolated containers.
+ */
+#[derive(Clone, Debug)]
+struct MockState {
+    cc_type: i32,
+    cc_report: Vec<u8>,
+    algo_id: u32,
+    measurements: BTreeMap<u32, TcgDigest>,
+    event_logs: Vec<TcgEventlog>,
+    mode: IsolationMode,
+    ima_enabled: bool,
+    ready: bool,
+    // The status every call fails with instead.
+    error: Option<(Code, String)>,
+    // How long every call takes to be answered.
+    delay: Option<Duration>,
+    requests: Vec<MockRequest>,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        let algo_id = tcg::TPM_ALG_SHA384.into();
+        let measurements = (0..=replay::CONTAINER_IMR)
+            .map(|index| {
+                let digest = TcgDigest {
+                    algo_id,
+                    hash: vec![0; 48],
+                };
+                (index, digest)
+            })
+            .collect();
+        MockState {
+            cc_type: 1,
+            cc_report: vec![],
+            algo_id,
+            measurements,
+            event_logs: vec![],
+            mode: IsolationMode::Isolated,
+            ima_enabled: true,
+            ready: true,
+            error: None,
+            delay: None,
+            requests: vec![],
+        }
+    }
+}
+
+impl MockState {
+    fn unfiltered(&self) -> bool {
+        self.mode != IsolationMode::Isolated
+    }
+
+    fn receive(
+        &mut self,
+        method: &'static str,
+        container_id: &str,
+        nonce: &Option<String>,
+        user_data: &Option<String>,
+    ) -> Result<(), Error> {
+        self.requests.push(MockRequest {
+            method,
+            container_id: container_id.to_string(),
+            nonce: nonce.clone(),
+            user_data: user_data.clone(),
+        });
+        match &self.error {
+            Some((code, message)) => Err(Status::new(*code, message.clone()).into()),
+            None => Ok(()),
+        }
+    }
+
+    // The event logs from start, at most count of them, checked as cima-server does.
+    fn event_logs(&self, start: Option<u32>, count: Option<u32>) -> Result<&[TcgEventlog], Error> {
+        let start = start.unwrap_or(0) as usize;
+        if start > self.event_logs.len() {
+            return Err(Status::invalid_argument(format!(
+                "Invalid input start. Current number of eventlog is {}",
+                self.event_logs.len()
+            ))
+            .into());
+        }
+        let end = match count {
+            Some(0) => {
+                return Err(Status::invalid_argument(
+                    "Invalid input count. count must be number larger than 0!",
+                )
+                .into())
+            }
+            Some(c) => (start + c as usize).min(self.event_logs.len()),
+            None => self.event_logs.len(),
+        };
+        Ok(&self.event_logs[start..end])
+    }
+}
+
+// Errors carrying a gRPC status keep their code, the others are reported as internal errors.
+fn to_status(e: Error) -> Status {
+    match e.downcast::<Status>() {
+        Ok(s) => s,
+        Err(e) => Status::internal(e.to_string()),
+    }
+}
+
+#[derive(Clone, Default)]
+struct MockService {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockService {
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("Mock state lock() failed.")
+    }
+}
+
+#[tonic::async_trait]
+impl Cima for MockService {
+    async fn get_default_algorithm(
+        &self,
+        _request: Request<GetDefaultAlgorithmRequest>,
+    ) -> Result<Response<GetDefaultAlgorithmResponse>, Status> {
+        let mut state = self.state();
+        state
+            .receive("GetDefaultAlgorithm", "", &None, &None)
+            .map_err(to_status)?;
+        Ok(Response::new(GetDefaultAlgorithmResponse {
+            algo_id: state.algo_id,
+        }))
+    }
+
+    async fn get_measurement_count(
+        &self,
+        _request: Request<GetMeasurementCountRequest>,
+    ) -> Result<Response<GetMeasurementCountResponse>, Status> {
+        let mut state = self.state();
+        state
+            .receive("GetMeasurementCount", "", &None, &None)
+            .map_err(to_status)?;
+        Ok(Response::new(GetMeasurementCountResponse {
+            count: state.measurements.len() as u32,
+        }))
+    }
+
+    async fn get_cc_report(
+        &self,
+        request: Request<GetCcReportRequest>,
+    ) -> Result<Response<GetCcReportResponse>, Status> {
+        let req = request.get_ref();
+        let mut state = self.state();
+        state
+            .receive("GetCcReport", &req.container_id, &req.nonce, &req.user_data)
+            .map_err(to_status)?;
+        Ok(Response::new(GetCcReportResponse {
+            cc_type: state.cc_type,
+            cc_report: state.cc_report.clone(),
+            unfiltered: state.unfiltered(),
+            binding: None,
+        }))
+    }
+
+    async fn get_cc_measurement(
+        &self,
+        request: Request<GetCcMeasurementRequest>,
+    ) -> Result<Response<GetCcMeasurementResponse>, Status> {
+        let req = request.get_ref();
+        let mut state = self.state();
+        state
+            .receive("GetCcMeasurement", &req.container_id, &None, &None)
+            .map_err(to_status)?;
+        match state.measurements.get(&req.index) {
+            Some(v) => Ok(Response::new(GetCcMeasurementResponse {
+                measurement: Some(v.clone()),
+                unfiltered: state.unfiltered(),
+            })),
+            None => Err(Status::invalid_argument(format!(
+                "Invalid IMR index {}.",
+                req.index
+            ))),
+        }
+    }
+
+    async fn get_cc_eventlog(
+        &self,
+        request: Request<GetCcEventlogRequest>,
+    ) -> Result<Response<GetCcEventlogResponse>, Status> {
+        let req = request.get_ref();
+        let mut state = self.state();
+        state
+            .receive("GetCcEventlog", &req.container_id, &None, &None)
+            .map_err(to_status)?;
+        let start = req.start.unwrap_or(0);
+        let event_logs = state.event_logs(req.start, req.count).map_err(to_status)?;
+
+        let mut response = GetCcEventlogResponse {
+            unfiltered: state.unfiltered(),
+            ..Default::default()
+        };
+        match req.format() {
+            EventlogFormat::Tcg => response.event_logs = event_logs.to_vec(),
+            EventlogFormat::CelJson => {
+                response.cel_event_logs = cel::to_json(event_logs, start).map_err(to_status)?
+            }
+            EventlogFormat::CelCbor => {
+                response.cel_event_logs = cel::to_cbor(event_logs, start).map_err(to_status)?
+            }
+            EventlogFormat::ImaAscii => {
+                return Err(Status::unimplemented(
+                    "The mock server does not serve the IMA ascii event logs.",
+                ))
+            }
+        }
+        Ok(Response::new(response))
+    }
+
+    async fn get_isolation_mode(
+        &self,
+        _request: Request<GetIsolationModeRequest>,
+    ) -> Result<Response<GetIsolationModeResponse>, Status> {
+        let mut state = self.state();
+        state
+            .receive("GetIsolationMode", "", &None, &None)
+            .map_err(to_status)?;
+        Ok(Response::new(GetIsolationModeResponse {
+            mode: state.mode.into(),
+            ima_enabled: state.ima_enabled,
+        }))
+    }
+
+    async fn get_server_info(
+        &self,
+        _request: Request<GetServerInfoRequest>,
+    ) -> Result<Response<GetServerInfoResponse>, Status> {
+        let mut state = self.state();
+        state
+            .receive("GetServerInfo", "", &None, &None)
+            .map_err(to_status)?;
+        Ok(Response::new(GetServerInfoResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: "mock".to_string(),
+            cc_type: state.cc_type,
+            ima_enabled: state.ima_enabled,
+            container_isolated: state.mode == IsolationMode::Isolated,
+            isolation_mode: state.mode.into(),
+            algo_ids: vec![state.algo_id],
+            imr_indexes: state.measurements.keys().copied().collect(),
+        }))
+    }
+
+    async fn get_evidence(
+        &self,
+        request: Request<GetEvidenceRequest>,
+    ) -> Result<Response<GetEvidenceResponse>, Status> {
+        let req = request.get_ref();
+        let mut state = self.state();
+        state
+            .receive("GetEvidence", &req.container_id, &req.nonce, &req.user_data)
+            .map_err(to_status)?;
+        Ok(Response::new(GetEvidenceResponse {
+            cc_type: state.cc_type,
+            cc_report: state.cc_report.clone(),
+            measurements: state
+                .measurements
+                .iter()
+                .map(|(index, v)| ImrMeasurement {
+                    index: *index,
+                    measurement: Some(v.clone()),
+                })
+                .collect(),
+            event_logs: state.event_logs.clone(),
+            unfiltered: state.unfiltered(),
+            binding: None,
+        }))
+    }
+
+    async fn extend_container_measurement(
+        &self,
+        request: Request<ExtendContainerMeasurementRequest>,
+    ) -> Result<Response<ExtendContainerMeasurementResponse>, Status> {
+        let req = request.get_ref();
+        let mut state = self.state();
+        state
+            .receive(
+                "ExtendContainerMeasurement",
+                &req.container_id,
+                &None,
+                &None,
+            )
+            .map_err(to_status)?;
+        Err(Status::unimplemented(
+            "The mock server does not extend the container measurement.",
+        ))
+    }
+
+    async fn get_readiness(
+        &self,
+        _request: Request<GetReadinessRequest>,
+    ) -> Result<Response<GetReadinessResponse>, Status> {
+        let mut state = self.state();
+        state
+            .receive("GetReadiness", "", &None, &None)
+            .map_err(to_status)?;
+        Ok(Response::new(GetReadinessResponse {
+            ready: state.ready,
+            checks: vec![],
+        }))
+    }
+}
+
+/*
+ * An in-process cima-server for the tests of the SDK users. It serves the scripted
+ * evidence on a Unix socket in a private temporary directory, on the runtime of the SDK,
+ * until it is dropped.
+ */
+pub struct MockServer {
+    address: String,
+    dir: Option<PathBuf>,
+    service: MockService,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    pub fn start() -> Result<MockServer, Error> {
+        let dir = std::env::temp_dir().join(format!(
+            "cima-mock-{}-{}",
+            std::process::id(),
+            MOCK_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+
+        match MockServer::start_at(&dir.join("cima-server.sock")) {
+            Ok(mut server) => {
+                server.dir = Some(dir);
+                Ok(server)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                Err(e)
+            }
+        }
+    }
+
+    // Serve on the socket path, e.g. one a client already waits for. It is removed on drop.
+    pub fn start_at(sock: &Path) -> Result<MockServer, Error> {
+        let listener = {
+            let _guard = RUNTIME.enter();
+            UnixListener::bind(sock)?
+        };
+        let service = MockService::default();
+        let state = service.state.clone();
+        let delay = layer_fn(move |inner: tonic::transport::server::Routes| {
+            let state = state.clone();
+            inner.and_then(move |response| async move {
+                let delay = state.lock().expect("Mock state lock() failed.").delay;
+                if let Some(v) = delay {
+                    tokio::time::sleep(v).await;
+                }
+                Ok::<_, tonic::codegen::StdError>(response)
+            })
+        });
+        let (shutdown, signal) = oneshot::channel();
+        RUNTIME.spawn(
+            Server::builder()
+                .layer(delay)
+                .add_service(CimaServer::new(service.clone()))
+                .serve_with_incoming_shutdown(UnixListenerStream::new(listener), async {
+                    let _ = signal.await;
+                }),
+        );
+
+        Ok(MockServer {
+            address: sock.to_string_lossy().to_string(),
+            dir: None,
+            service,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    // Options of a client of the mock server, sending MOCK_CONTAINER_ID.
+    pub fn client(&self) -> ClientBuilder {
+        ClientBuilder::new()
+            .address(&self.address)
+            .container_id(MOCK_CONTAINER_ID)
+    }
+
+    pub fn set_cc_type(&self, cc_type: i32) {
+        self.service.state().cc_type = cc_type;
+    }
+
+    pub fn set_cc_report(&self, cc_report: Vec<u8>) {
+        self.service.state().cc_report = cc_report;
+    }
+
+    // Replace the IMRs, the algorithm is the one of the first measurement.
+    pub fn set_measurements(&self, measurements: Vec<(u32, TcgDigest)>) {
+        let mut state = self.service.state();
+        if let Some((_, v)) = measurements.first() {
+            state.algo_id = v.algo_id;
+        }
+        state.measurements = measurements.into_iter().collect();
+    }
+
+    // Replace the event logs, the IMRs are left as they are.
+    pub fn set_event_logs(&self, event_logs: Vec<TcgEventlog>) {
+        self.service.state().event_logs = event_logs;
+    }
+
+    /*
+     * Append an event and extend its IMR with it, so the event logs keep replaying to the
+     * measurements. The container IMR is extended with all the digests of the event, as
+     * cima-server does.
+     */
+    pub fn push_event(&self, event: TcgEventlog) -> Result<(), Error> {
+        let mut state = self.service.state();
+        let container_scoped = !state.unfiltered();
+        let measurement = match state.measurements.get_mut(&event.imr_index) {
+            Some(v) => v,
+            None => return Err(anyhow::anyhow!("No IMR {} to extend.", event.imr_index)),
+        };
+
+        let events = [event];
+        for digests in replay::extended_digests(
+            events[0].imr_index,
+            measurement.algo_id,
+            &events,
+            container_scoped,
+        ) {
+            for digest in digests {
+                measurement.hash = hash(
+                    replay::message_digest(digest.algo_id)?,
+                    &[measurement.hash.clone(), digest.hash.clone()].concat(),
+                )?
+                .to_vec();
+            }
+        }
+
+        let [mut event] = events;
+        event.rec_num = state.event_logs.len() as u32;
+        state.event_logs.push(event);
+        Ok(())
+    }
+
+    pub fn set_isolation_mode(&self, mode: IsolationMode, ima_enabled: bool) {
+        let mut state = self.service.state();
+        state.mode = mode;
+        state.ima_enabled = ima_enabled;
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.service.state().ready = ready;
+    }
+
+    // Fail every call with the status, until it is set back to None.
+    pub fn set_error(&self, error: Option<(Code, &str)>) {
+        self.service.state().error = error.map(|(code, message)| (code, message.to_string()));
+    }
+
+    // Answer every call after the delay, e.g. to let the client time out.
+    pub fn set_delay(&self, delay: Option<Duration>) {
+        self.service.state().delay = delay;
+    }
+
+    // The requests received so far.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.service.state().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(v) = self.shutdown.take() {
+            let _ = v.send(());
+        }
+        let _ = match &self.dir {
+            Some(v) => std::fs::remove_dir_all(v),
+            None => std::fs::remove_file(&self.address),
+        };
+    }
+}
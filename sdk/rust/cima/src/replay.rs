@@ -0,0 +1,176 @@
+use crate::client::cima_server_pb::{EventlogFormat, TcgDigest, TcgEventlog};
+use crate::client::CimaAsyncClient;
+use anyhow::{anyhow, Error};
+use evidence_api::tcg;
+use openssl::hash::{hash, MessageDigest};
+
+// The virtual IMR cima-server keeps for each container.
+pub const CONTAINER_IMR: u32 = 3;
+
+// The replay of the event logs of an IMR, compared with its measurement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImrReplay {
+    pub index: u32,
+    pub algo_id: u32,
+    pub measurement: Vec<u8>,
+    // The value replayed from all the events of the IMR.
+    pub replayed: Vec<u8>,
+    pub event_count: usize,
+    // The number of the first events replaying to the measurement. The log may hold
+    // events extended after the measurement was read.
+    pub matched_events: Option<usize>,
+}
+
+impl ImrReplay {
+    pub fn matches(&self) -> bool {
+        self.matched_events.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    // Whether the event logs are not scoped to the container of the caller.
+    pub unfiltered: bool,
+    pub imrs: Vec<ImrReplay>,
+}
+
+impl ReplayReport {
+    // Whether all the IMRs replay to their measurements.
+    pub fn verified(&self) -> bool {
+        self.imrs.iter().all(|v| v.matches())
+    }
+
+    pub fn mismatched_imrs(&self) -> Vec<u32> {
+        self.imrs
+            .iter()
+            .filter(|v| !v.matches())
+            .map(|v| v.index)
+            .collect()
+    }
+}
+
+pub(crate) fn message_digest(algo_id: u32) -> Result<MessageDigest, Error> {
+    let algo_id: u16 = algo_id.try_into()?;
+    match algo_id {
+        tcg::TPM_ALG_SHA1 => Ok(MessageDigest::sha1()),
+        tcg::TPM_ALG_SHA256 => Ok(MessageDigest::sha256()),
+        tcg::TPM_ALG_SHA384 => Ok(MessageDigest::sha384()),
+        tcg::TPM_ALG_SHA512 => Ok(MessageDigest::sha512()),
+        _ => Err(anyhow!(
+            "[message_digest] unsupported hash algorithm {}",
+            algo_id
+        )),
+    }
+}
+
+/*
+ * The digests extended into the IMR by each event. A TCG IMR is extended with the digest
+ * of its algorithm, skipping EV_NO_ACTION. The container IMR of cima-server is extended
+ * with all the digests of each event, each with its own algorithm, as
+ * Container::extend_imr does.
+ */
+pub(crate) fn extended_digests(
+    index: u32,
+    algo_id: u32,
+    events: &[TcgEventlog],
+    container_scoped: bool,
+) -> Vec<Vec<&TcgDigest>> {
+    let events = events.iter().filter(|e| e.imr_index == index);
+    if container_scoped && index == CONTAINER_IMR {
+        return events.map(|e| e.digests.iter().collect()).collect();
+    }
+
+    events
+        .filter(|e| e.event_type != tcg::EV_NO_ACTION)
+        .filter_map(|e| e.digests.iter().find(|d| d.algo_id == algo_id))
+        .map(|d| vec![d])
+        .collect()
+}
+
+// Replay the events of an IMR from zero and compare the result with its measurement.
+pub fn replay_imr(
+    index: u32,
+    measurement: &TcgDigest,
+    events: &[TcgEventlog],
+    container_scoped: bool,
+) -> Result<ImrReplay, Error> {
+    let extended = extended_digests(index, measurement.algo_id, events, container_scoped);
+
+    let mut imr = vec![0; measurement.hash.len()];
+    let mut matched_events = if imr == measurement.hash {
+        Some(0)
+    } else {
+        None
+    };
+    for (count, digests) in extended.iter().enumerate() {
+        for digest in digests {
+            if digest.hash.len() != imr.len() {
+                return Err(anyhow!(
+                    "[replay_imr] the digest of algorithm {} does not fit IMR {}",
+                    digest.algo_id,
+                    index
+                ));
+            }
+            imr = hash(
+                message_digest(digest.algo_id)?,
+                &[imr, digest.hash.clone()].concat(),
+            )?
+            .to_vec();
+        }
+        if matched_events.is_none() && imr == measurement.hash {
+            matched_events = Some(count + 1);
+        }
+    }
+
+    Ok(ImrReplay {
+        index,
+        algo_id: measurement.algo_id,
+        measurement: measurement.hash.clone(),
+        replayed: imr,
+        event_count: extended.len(),
+        matched_events,
+    })
+}
+
+// Replay the events of each measured IMR.
+pub fn replay(
+    measurements: &[(u32, TcgDigest)],
+    events: &[TcgEventlog],
+    container_scoped: bool,
+) -> Result<ReplayReport, Error> {
+    let mut imrs = vec![];
+    for (index, measurement) in measurements {
+        imrs.push(replay_imr(*index, measurement, events, container_scoped)?);
+    }
+
+    Ok(ReplayReport {
+        unfiltered: !container_scoped,
+        imrs,
+    })
+}
+
+/*
+ * Fetch the measurements of the IMRs visible to the caller, then its event logs, and
+ * replay them. The measurements are read first, so an IMR matches when the first of its
+ * events replay to the measurement.
+ */
+pub async fn verify(client: &CimaAsyncClient) -> Result<ReplayReport, Error> {
+    let info = client.get_server_info().await?;
+    let algo_id = client.get_default_algorithm().await?.algo_id;
+
+    let mut measurements = vec![];
+    for index in info.imr_indexes {
+        let response = client
+            .get_cc_measurement(index.try_into()?, algo_id.try_into()?)
+            .await?;
+        match response.measurement {
+            Some(v) => measurements.push((index, v)),
+            None => return Err(anyhow!("[verify] no measurement of IMR {}", index)),
+        }
+    }
+
+    let response = client
+        .get_cc_eventlog(None, None, EventlogFormat::Tcg)
+        .await?;
+    replay(&measurements, &response.event_logs, !response.unfiltered)
+}
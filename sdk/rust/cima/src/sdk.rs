@@ -1,15 +1,35 @@
-use crate::client::CimaServiceClient;
+use crate::client::{CimaServiceClient, ClientBuilder};
+use crate::eventlog::to_event_log_entry;
 use anyhow::*;
 use evidence_api::api::EvidenceApi;
 use evidence_api::api_data::{Algorithm, CcReport, ExtraArgs};
 use evidence_api::binary_blob::dump_data;
 use evidence_api::tcg::*;
 use core::result::Result::Ok;
+use std::sync::RwLock;
 
-const UDS_PATH: &str = "/run/cima/uds/cima-server.sock";
+lazy_static! {
+    // The client of the API functions, connected to CIMA_SOCK or the default socket.
+    static ref DEFAULT_CLIENT: RwLock<CimaServiceClient> =
+        RwLock::new(ClientBuilder::new().build());
+}
 
 pub struct API {}
 
+impl API {
+    // Replace the client of the API functions for the whole process.
+    pub fn set_client(client: CimaServiceClient) {
+        *DEFAULT_CLIENT.write().expect("CIMA client lock failed.") = client;
+    }
+
+    pub fn client() -> CimaServiceClient {
+        DEFAULT_CLIENT
+            .read()
+            .expect("CIMA client lock failed.")
+            .clone()
+    }
+}
+
 impl EvidenceApi for API {
     // EvidenceApi trait function: get cc report from CIMA server
     fn get_cc_report(
@@ -17,15 +37,13 @@ impl EvidenceApi for API {
         data: Option<String>,
         extra_args: ExtraArgs,
     ) -> Result<CcReport, anyhow::Error> {
-        let mut cima_service_client = CimaServiceClient {
-            cima_uds_path: UDS_PATH.to_string(),
-        };
+        let mut cima_service_client = API::client();
 
         let response = match cima_service_client.get_cc_report_from_server(nonce, data, extra_args)
         {
             Ok(r) => r,
             Err(e) => {
-                return Err(anyhow!("[get_cc_report] err get cc report: {:?}", e));
+                return Err(Error::new(e).context("[get_cc_report] err get cc report"));
             }
         };
 
@@ -43,36 +61,34 @@ impl EvidenceApi for API {
 
     // EvidenceApi trait function: get max number of IMRs
     fn get_measurement_count() -> Result<u8, anyhow::Error> {
-        let mut cima_service_client = CimaServiceClient {
-            cima_uds_path: UDS_PATH.to_string(),
-        };
+        let mut cima_service_client = API::client();
 
         let response = match cima_service_client.get_cc_measurement_count_from_server() {
             Ok(r) => r,
             Err(e) => {
-                return Err(anyhow!(
-                    "[get_measurement_count] err get cc measurement count: {:?}",
-                    e
-                ));
+                return Err(
+                    Error::new(e).context("[get_measurement_count] err get cc measurement count")
+                );
             }
         };
 
-        Ok(response.count.try_into().unwrap())
+        match response.count.try_into() {
+            Ok(v) => Ok(v),
+            Err(_) => Err(anyhow!(
+                "[get_measurement_count] invalid measurement count {}",
+                response.count
+            )),
+        }
     }
 
     // EvidenceApi trait function: get measurements
     fn get_cc_measurement(index: u8, algo_id: u16) -> Result<TcgDigest, anyhow::Error> {
-        let mut cima_service_client = CimaServiceClient {
-            cima_uds_path: UDS_PATH.to_string(),
-        };
+        let mut cima_service_client = API::client();
 
         let response = match cima_service_client.get_cc_measurement_from_server(index, algo_id) {
             Ok(r) => r,
             Err(e) => {
-                return Err(anyhow!(
-                    "[get_cc_measurement] err get cc measurement: {:?}",
-                    e
-                ));
+                return Err(Error::new(e).context("[get_cc_measurement] err get cc measurement"));
             }
         };
 
@@ -92,44 +108,18 @@ impl EvidenceApi for API {
         start: Option<u32>,
         count: Option<u32>,
     ) -> Result<Vec<EventLogEntry>, anyhow::Error> {
-        let mut cima_service_client = CimaServiceClient {
-            cima_uds_path: UDS_PATH.to_string(),
-        };
+        let mut cima_service_client = API::client();
 
         let response = match cima_service_client.get_cc_eventlog_from_server(start, count) {
             Ok(r) => r,
             Err(e) => {
-                return Err(anyhow!("[get_cc_eventlog] err get cc eventlog: {:?}", e));
+                return Err(Error::new(e).context("[get_cc_eventlog] err get cc eventlog"));
             }
         };
 
         let mut event_logs: Vec<EventLogEntry> = Vec::new();
-
         for el in response.event_logs {
-            if !el.digests.is_empty() {
-                let mut digests: Vec<TcgDigest> = Vec::new();
-                for d in el.digests {
-                    digests.push(TcgDigest {
-                        algo_id: d.algo_id as u16,
-                        hash: d.hash,
-                    });
-                }
-                event_logs.push(EventLogEntry::TcgImrEvent(TcgImrEvent {
-                    imr_index: el.imr_index,
-                    event_type: el.event_type,
-                    digests: digests.clone(),
-                    event_size: el.event_size,
-                    event: el.event.clone(),
-                }));
-            } else {
-                event_logs.push(EventLogEntry::TcgPcClientImrEvent(TcgPcClientImrEvent {
-                    imr_index: el.imr_index,
-                    event_type: el.event_type,
-                    digest: el.digests[0].hash[0..20].try_into().unwrap(),
-                    event_size: el.event_size,
-                    event: el.event.clone(),
-                }));
-            }
+            event_logs.push(to_event_log_entry(el)?);
         }
 
         Ok(event_logs)
@@ -137,24 +127,29 @@ impl EvidenceApi for API {
 
     // EvidenceApi trait function: get default algorithm
     fn get_default_algorithm() -> Result<Algorithm, anyhow::Error> {
-        let mut cima_service_client = CimaServiceClient {
-            cima_uds_path: UDS_PATH.to_string(),
-        };
+        let mut cima_service_client = API::client();
 
         let response = match cima_service_client.get_cc_default_algorithm_from_server() {
             Ok(r) => r,
             Err(e) => {
-                return Err(anyhow!(
-                    "[get_default_algorithm] err get cc get default algorithm: {:?}",
-                    e
-                ));
+                return Err(Error::new(e)
+                    .context("[get_default_algorithm] err get cc get default algorithm"));
             }
         };
 
         let algo_id = response.algo_id as u16;
+        let algo_id_str = match ALGO_NAME_MAP.get(&algo_id) {
+            Some(v) => v.to_owned(),
+            None => {
+                return Err(anyhow!(
+                    "[get_default_algorithm] unknown algorithm {}",
+                    algo_id
+                ))
+            }
+        };
         Ok(Algorithm {
             algo_id,
-            algo_id_str: ALGO_NAME_MAP.get(&algo_id).unwrap().to_owned(),
+            algo_id_str,
         })
     }
 }
@@ -0,0 +1,26 @@
+use std::io;
+
+// The vsock connection, shared with cima-server in cima-common.
+pub use cima_common::vsock::VsockStream;
+
+// Address prefix of the vsock server, e.g. "vsock://3:4050" for the CID 3 and the port 4050.
+pub const VSOCK_SCHEME: &str = "vsock://";
+
+// Parse a "vsock://<cid>:<port>" address, None is returned for the other addresses.
+pub fn parse_address(address: &str) -> Option<io::Result<(u32, u32)>> {
+    let address = address.strip_prefix(VSOCK_SCHEME)?;
+    let parsed = match address.split_once(':') {
+        Some((cid, port)) => match (cid.parse::<u32>(), port.parse::<u32>()) {
+            (Ok(cid), Ok(port)) => Some((cid, port)),
+            _ => None,
+        },
+        None => None,
+    };
+
+    Some(parsed.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid vsock address {}{}", VSOCK_SCHEME, address),
+        )
+    }))
+}
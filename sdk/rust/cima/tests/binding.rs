@@ -0,0 +1,93 @@
+/*
+ * The report data of a container report checked by the SDK verifier. BINDING_DIGEST is
+ * the nonce cima-server gives to the backend for binding() with NONCE and USER_DATA.
+ */
+use cima::binding::{expected_report_data, verify_tdx_report_data, BINDING_VERSION};
+use cima::client::cima_server_pb::{ReportBinding, TcgDigest};
+use evidence_api::tcg::TPM_ALG_SHA384;
+use openssl::hash::{hash, MessageDigest};
+
+const NONCE: &[u8] = b"nonce";
+const USER_DATA: &[u8] = b"user data";
+const BINDING_DIGEST: &str = "5efc92c6b60eaa50bf3dc88b294c0b63a25c5e388c14ce556cc15277c5202a243d336088ae9a21720cb8be1dea40ce05";
+
+fn binding() -> ReportBinding {
+    let imr = TcgDigest {
+        algo_id: TPM_ALG_SHA384.into(),
+        hash: vec![3; 48],
+    };
+    ReportBinding {
+        version: BINDING_VERSION,
+        container_id: "container".to_string(),
+        container_imr: Some(imr),
+        event_count: 7,
+    }
+}
+
+fn binding_digest() -> Vec<u8> {
+    (0..BINDING_DIGEST.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&BINDING_DIGEST[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// The TDX backend signs SHA512 of the nonce it is given, the binding digest.
+fn tdx_report_data() -> Vec<u8> {
+    hash(MessageDigest::sha512(), &binding_digest())
+        .unwrap()
+        .to_vec()
+}
+
+fn verify(binding: &ReportBinding, nonce: &[u8], report_data: &[u8]) -> bool {
+    verify_tdx_report_data(
+        binding,
+        Some(base64::encode(nonce)),
+        Some(base64::encode(USER_DATA)),
+        report_data,
+    )
+    .unwrap()
+}
+
+#[test]
+fn binding_round_trip() {
+    let binding = binding();
+    let report_data = tdx_report_data();
+    assert_eq!(report_data.len(), 64);
+    assert!(verify(&binding, NONCE, &report_data));
+
+    let expected = expected_report_data(
+        &binding,
+        Some(base64::encode(NONCE)),
+        Some(base64::encode(USER_DATA)),
+    )
+    .unwrap();
+    assert_eq!(expected, binding_digest());
+}
+
+#[test]
+fn binding_mismatch() {
+    let binding = binding();
+    let report_data = tdx_report_data();
+
+    assert!(!verify(&binding, b"other nonce", &report_data));
+
+    let mut other = binding.clone();
+    other.container_imr.as_mut().unwrap().hash[0] ^= 1;
+    assert!(!verify(&other, NONCE, &report_data));
+
+    let mut other = binding.clone();
+    other.event_count += 1;
+    assert!(!verify(&other, NONCE, &report_data));
+
+    let mut other = binding.clone();
+    other.container_id = String::from("other");
+    assert!(!verify(&other, NONCE, &report_data));
+
+    let mut other = binding.clone();
+    other.version += 1;
+    assert!(verify_tdx_report_data(&other, None, None, &report_data).is_err());
+
+    let mut other = binding;
+    other.container_imr = None;
+    assert!(verify_tdx_report_data(&other, None, None, &report_data).is_err());
+}
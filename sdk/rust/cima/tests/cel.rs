@@ -0,0 +1,87 @@
+/*
+ * The CEL records of the SDK TcgEventlog, the encodings themselves are shared with
+ * cima-server and checked against golden records in cima-common.
+ */
+use cima::cel::{self, CelContent, CelRecord, IMA_TEMPLATE_NAME};
+use cima::client::cima_server_pb::{TcgDigest, TcgEventlog};
+use evidence_api::tcg::{EV_NO_ACTION, IMA_MEASUREMENT_EVENT, TPM_ALG_SHA256, TPM_ALG_SHA384};
+
+fn event(imr_index: u32, event_type: u32, event: &[u8], digests: &[(u16, usize)]) -> TcgEventlog {
+    TcgEventlog {
+        imr_index,
+        event_type,
+        event_size: event.len() as u32,
+        event: event.to_vec(),
+        digests: digests
+            .iter()
+            .map(|(algo_id, size)| TcgDigest {
+                algo_id: (*algo_id).into(),
+                hash: vec![*size as u8; *size],
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cel_records_of_event_logs() {
+    let record = CelRecord::new(
+        4,
+        &event(
+            1,
+            0x80000001,
+            &[1, 2, 3],
+            &[(TPM_ALG_SHA256, 32), (TPM_ALG_SHA384, 48)],
+        ),
+    );
+    assert_eq!(record.recnum, 4);
+    assert_eq!(record.pcr, 1);
+    assert_eq!(
+        record.digests,
+        vec![
+            (TPM_ALG_SHA256.into(), vec![32; 32]),
+            (TPM_ALG_SHA384.into(), vec![48; 48])
+        ]
+    );
+    assert_eq!(
+        record.content,
+        CelContent::PcClientStd {
+            event_type: 0x80000001,
+            event_data: vec![1, 2, 3],
+        }
+    );
+
+    let ima = format!(
+        "/usr/bin/sh /kubepods.slice sha256:{} /usr/bin/bash",
+        "ab".repeat(32)
+    );
+    let ima = event(
+        3,
+        IMA_MEASUREMENT_EVENT,
+        ima.as_bytes(),
+        &[(TPM_ALG_SHA384, 48)],
+    );
+    assert_eq!(
+        CelRecord::new(0, &ima).content,
+        CelContent::ImaTemplate {
+            template_name: IMA_TEMPLATE_NAME.to_string(),
+            template_data: cel::ima_template_data(&ima.event).unwrap(),
+        }
+    );
+}
+
+#[test]
+fn cel_encodings_of_event_logs() {
+    let event_logs = vec![event(
+        0,
+        EV_NO_ACTION,
+        b"Spec ID Event03\0",
+        &[(TPM_ALG_SHA256, 32)],
+    )];
+    let json = String::from_utf8(cel::to_json(&event_logs, 5).unwrap()).unwrap();
+    assert!(json.contains("\"recnum\":5"));
+    assert!(!cel::to_cbor(&event_logs, 5).unwrap().is_empty());
+
+    let event_logs = vec![event(0, EV_NO_ACTION, &[], &[(0x12, 32)])];
+    assert!(cel::to_json(&event_logs, 0).is_err());
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "mock")]
+
+use cima::client::{ClientBuilder, SOCK_ENV};
+use cima::error::CimaError;
+use cima::mock::{MockServer, MOCK_CONTAINER_ID};
+use cima::sdk::API;
+use evidence_api::api::EvidenceApi;
+use evidence_api::api_data::ExtraArgs;
+use std::time::{Duration, Instant};
+use tonic::Code;
+
+#[test]
+fn client_retries_until_the_server_starts() {
+    let sock = std::env::temp_dir().join(format!("cima-client-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&sock);
+    let mut client = ClientBuilder::new()
+        .address(sock.to_str().unwrap())
+        .container_id(MOCK_CONTAINER_ID)
+        .build();
+    assert!(matches!(
+        client.get_cc_measurement_count_from_server(),
+        Err(CimaError::ServerUnavailable(_))
+    ));
+
+    let starting = {
+        let sock = sock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            MockServer::start_at(&sock).unwrap()
+        })
+    };
+    let mut client = ClientBuilder::new()
+        .address(sock.to_str().unwrap())
+        .container_id(MOCK_CONTAINER_ID)
+        .retry(10, Duration::from_millis(50))
+        .build();
+    let count = client.get_cc_measurement_count_from_server().unwrap();
+    assert_eq!(count.count, 4);
+
+    drop(starting.join().unwrap());
+    assert!(!sock.exists());
+}
+
+#[test]
+fn client_retries_unavailable_calls() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.client().retry(2, Duration::from_millis(10)).build();
+
+    server.set_error(Some((Code::Unavailable, "down")));
+    assert_eq!(
+        client.get_readiness_from_server().err(),
+        Some(CimaError::ServerUnavailable("down".to_string()))
+    );
+    assert_eq!(server.requests().len(), 3);
+
+    // The other errors are not retried.
+    server.set_error(Some((Code::PermissionDenied, "denied")));
+    assert!(client.get_readiness_from_server().is_err());
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[test]
+fn client_request_timeout() {
+    let server = MockServer::start().unwrap();
+    server.set_delay(Some(Duration::from_secs(2)));
+    let mut client = server
+        .client()
+        .request_timeout(Duration::from_millis(100))
+        .build();
+
+    let start = Instant::now();
+    assert!(matches!(
+        client.get_readiness_from_server(),
+        Err(CimaError::ServerUnavailable(_))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn client_address_from_env() {
+    let server = MockServer::start().unwrap();
+    std::env::set_var(SOCK_ENV, server.address());
+    let mut client = ClientBuilder::new().container_id(MOCK_CONTAINER_ID).build();
+    std::env::remove_var(SOCK_ENV);
+
+    assert!(client.get_readiness_from_server().unwrap().ready);
+    assert_eq!(server.requests()[0].method, "GetReadiness");
+}
+
+#[test]
+fn api_set_client() {
+    let server = MockServer::start().unwrap();
+    server.set_cc_report(vec![1, 2, 3]);
+    API::set_client(server.client().build());
+
+    let report = API::get_cc_report(Some("bm9uY2U=".to_string()), None, ExtraArgs {}).unwrap();
+    assert_eq!(report.cc_report, vec![1, 2, 3]);
+    assert_eq!(API::get_measurement_count().unwrap(), 4);
+    assert_eq!(server.requests()[0].nonce.as_deref(), Some("bm9uY2U="));
+}
@@ -0,0 +1,101 @@
+/*
+ * The container IDs the SDK discovers. server_ids are the IDs the parser of cima-server
+ * assigns to the cgroup paths, and the SDK must find the same ones.
+ */
+use cima::container::{id_from_cgroup, id_from_mountinfo, parse_id_from_path};
+
+const POD_UID: &str = "8c0cbb8f_3f4a_4b56_9b8f_1d1f0e0a3f42";
+const CONTAINER_ID: &str = "5e5f0c1f2e3d4c5b6a79880716253443f2e1d0c9b8a7968574635241302f1e0d";
+
+#[test]
+fn container_id_from_cgroup_v2() {
+    let cgroup = format!(
+        "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod{}.slice/cri-containerd-{}.scope\n",
+        POD_UID, CONTAINER_ID
+    );
+    assert_eq!(id_from_cgroup(&cgroup).as_deref(), Some(POD_UID));
+
+    // A private cgroup namespace hides the path.
+    assert_eq!(id_from_cgroup("0::/\n"), None);
+}
+
+#[test]
+fn container_id_from_cgroup_v1() {
+    let cgroup = format!(
+        "12:pids:/system.slice/docker-{}.scope\n11:memory:/system.slice/docker-{}.scope\n0::/system.slice/containerd.service\n",
+        CONTAINER_ID, CONTAINER_ID
+    );
+    assert_eq!(id_from_cgroup(&cgroup).as_deref(), Some(CONTAINER_ID));
+    assert_eq!(
+        parse_id_from_path(&format!("/system.slice/docker-{}.scope", CONTAINER_ID)).as_deref(),
+        Some(CONTAINER_ID)
+    );
+    assert_eq!(
+        id_from_cgroup("1:name=systemd:/user.slice/session-1.scope\n"),
+        None
+    );
+}
+
+#[test]
+fn container_id_from_mountinfo() {
+    let kubelet = "2958 2938 253:1 /var/lib/kubelet/pods/8c0cbb8f-3f4a-4b56-9b8f-1d1f0e0a3f42/etc-hosts /etc/hosts rw,relatime - ext4 /dev/vda1 rw";
+    let docker = format!(
+        "1024 1001 253:1 /var/lib/docker/containers/{}/hostname /etc/hostname rw,relatime - ext4 /dev/vda1 rw",
+        CONTAINER_ID
+    );
+    let crio = format!(
+        "1025 1001 0:25 /containers/storage/overlay-containers/{}/userdata/hostname /etc/hostname rw,nosuid - tmpfs tmpfs rw",
+        CONTAINER_ID
+    );
+
+    assert_eq!(id_from_mountinfo(kubelet).as_deref(), Some(POD_UID));
+    assert_eq!(id_from_mountinfo(&docker).as_deref(), Some(CONTAINER_ID));
+    // The server measures no CRI-O or podman container outside of a pod.
+    assert_eq!(id_from_mountinfo(&crio), None);
+
+    // The pod UID is the ID of a CRI-O container of the pod.
+    let pod = format!("{}\n{}\n", crio, kubelet);
+    assert_eq!(id_from_mountinfo(&pod).as_deref(), Some(POD_UID));
+
+    assert_eq!(
+        id_from_mountinfo("22 1 253:1 / / rw,relatime - ext4 /dev/vda1 rw"),
+        None
+    );
+}
+
+#[test]
+fn container_id_matches_server() {
+    let server_ids = [
+        (
+            format!(
+                "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod{}.slice/cri-containerd-{}.scope",
+                POD_UID, CONTAINER_ID
+            ),
+            Some(POD_UID.to_string()),
+        ),
+        (
+            format!(
+                "/kubepods.slice/kubepods-pod{}.slice/crio-{}.scope",
+                POD_UID.replace('_', ""),
+                CONTAINER_ID
+            ),
+            Some(POD_UID.replace('_', "")),
+        ),
+        (
+            format!("/system.slice/docker-{}.scope", CONTAINER_ID),
+            Some(CONTAINER_ID.to_string()),
+        ),
+        (format!("/system.slice/crio-{}.scope", CONTAINER_ID), None),
+        (format!("/machine.slice/libpod-{}.scope", CONTAINER_ID), None),
+        ("/system.slice/containerd.service".to_string(), None),
+        (
+            "/user.slice/user-1000.slice/session-1.scope".to_string(),
+            None,
+        ),
+        ("/".to_string(), None),
+    ];
+
+    for (path, id) in server_ids.iter() {
+        assert_eq!(&parse_id_from_path(path), id, "{}", path);
+    }
+}
@@ -0,0 +1,67 @@
+use cima::client::ClientBuilder;
+use cima::error::CimaError;
+use std::io;
+use tokio::net::UnixStream;
+use tonic::transport::{Endpoint, Uri};
+use tonic::{Code, Status};
+use tower::service_fn;
+
+#[test]
+fn status_errors() {
+    for code in [Code::Unavailable, Code::DeadlineExceeded, Code::Cancelled] {
+        let error = CimaError::from(Status::new(code, "down"));
+        assert_eq!(error, CimaError::ServerUnavailable("down".to_string()));
+        assert!(error.retryable());
+    }
+    for code in [Code::PermissionDenied, Code::Unauthenticated] {
+        let error = CimaError::from(Status::new(code, "denied"));
+        assert_eq!(error, CimaError::PermissionDenied("denied".to_string()));
+        assert!(!error.retryable());
+    }
+    assert_eq!(
+        CimaError::from(Status::not_found("missing")),
+        CimaError::Server {
+            code: Code::NotFound,
+            message: "missing".to_string()
+        }
+    );
+}
+
+#[test]
+fn io_errors() {
+    let error = CimaError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+    assert!(matches!(error, CimaError::PermissionDenied(_)));
+    let error = CimaError::from(io::Error::from(io::ErrorKind::NotFound));
+    assert!(matches!(error, CimaError::ServerUnavailable(_)));
+}
+
+// The transport error of a connection failing with the error.
+async fn transport_error(kind: io::ErrorKind) -> CimaError {
+    let connector =
+        service_fn(move |_: Uri| async move { Err::<UnixStream, _>(io::Error::from(kind)) });
+    Endpoint::from_static("http://[::]:0")
+        .connect_with_connector(connector)
+        .await
+        .unwrap_err()
+        .into()
+}
+
+#[tokio::test]
+async fn transport_errors() {
+    // EACCES on the socket is not retried.
+    let error = transport_error(io::ErrorKind::PermissionDenied).await;
+    assert!(matches!(error, CimaError::PermissionDenied(_)));
+    assert!(!error.retryable());
+
+    let error = transport_error(io::ErrorKind::NotFound).await;
+    assert!(matches!(error, CimaError::ServerUnavailable(_)));
+    assert!(error.retryable());
+
+    let error = ClientBuilder::new()
+        .address("/nonexistent/cima.sock")
+        .connect()
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, CimaError::ServerUnavailable(_)));
+}
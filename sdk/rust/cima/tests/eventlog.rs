@@ -0,0 +1,203 @@
+/*
+ * The records below are the ones cima-server returns for the backend event log entries,
+ * TcgImrEvent records as they are and TcgPcClientImrEvent records with the pcclient
+ * format. The CEL records are encoded by the encoder the server uses.
+ */
+use cima::cel;
+use cima::client::cima_server_pb::{EventlogFormat, TcgDigest, TcgEventlog};
+use cima::eventlog::{
+    cel_to_event_log_entry, cel_to_tcg_eventlog, decode_cel, to_event_log_entry, EVENT_FORMAT_KEY,
+    PCCLIENT_FORMAT,
+};
+use evidence_api::tcg::{
+    EventLogEntry, EV_NO_ACTION, IMA_MEASUREMENT_EVENT, TPM_ALG_SHA1, TPM_ALG_SHA256,
+    TPM_ALG_SHA384,
+};
+
+fn record(
+    rec_num: u32,
+    imr_index: u32,
+    event_type: u32,
+    event: &[u8],
+    digests: &[(u16, u8, usize)],
+) -> TcgEventlog {
+    TcgEventlog {
+        rec_num,
+        imr_index,
+        event_type,
+        event_size: event.len() as u32,
+        event: event.to_vec(),
+        digests: digests
+            .iter()
+            .map(|(algo_id, byte, size)| TcgDigest {
+                algo_id: (*algo_id).into(),
+                hash: vec![*byte; *size],
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn ima_event() -> Vec<u8> {
+    format!(
+        "/usr/bin/sh /kubepods.slice/kubepods-pod1.slice sha384:{} /usr/bin/bash",
+        "01".repeat(48)
+    )
+    .into_bytes()
+}
+
+#[test]
+fn multi_digest_record() {
+    let event = record(
+        0,
+        1,
+        0x80000001,
+        &[4, 5, 6],
+        &[(TPM_ALG_SHA256, 1, 32), (TPM_ALG_SHA384, 2, 48)],
+    );
+
+    match to_event_log_entry(event).unwrap() {
+        EventLogEntry::TcgImrEvent(v) => {
+            assert_eq!(v.imr_index, 1);
+            assert_eq!(v.event_type, 0x80000001);
+            assert_eq!(v.event_size, 3);
+            assert_eq!(v.event, vec![4, 5, 6]);
+            assert_eq!(v.digests.len(), 2);
+            assert_eq!(v.digests[0].algo_id, TPM_ALG_SHA256);
+            assert_eq!(v.digests[0].hash, vec![1; 32]);
+            assert_eq!(v.digests[1].algo_id, TPM_ALG_SHA384);
+            assert_eq!(v.digests[1].hash, vec![2; 48]);
+        }
+        _ => panic!("Excepted a TcgImrEvent"),
+    }
+}
+
+#[test]
+fn ima_record() {
+    let data = ima_event();
+    let event = record(
+        0,
+        3,
+        IMA_MEASUREMENT_EVENT,
+        &data,
+        &[(TPM_ALG_SHA384, 7, 48)],
+    );
+
+    match to_event_log_entry(event).unwrap() {
+        EventLogEntry::TcgImrEvent(v) => {
+            assert_eq!(v.event_type, IMA_MEASUREMENT_EVENT);
+            assert_eq!(v.event, data);
+            assert_eq!(v.digests[0].algo_id, TPM_ALG_SHA384);
+        }
+        _ => panic!("Excepted a TcgImrEvent"),
+    }
+}
+
+#[test]
+fn pcclient_record() {
+    let mut event = record(0, 0, 0x3, &[1, 2], &[(TPM_ALG_SHA1, 9, 20)]);
+    event
+        .extra_info
+        .insert(EVENT_FORMAT_KEY.to_string(), PCCLIENT_FORMAT.to_string());
+
+    match to_event_log_entry(event).unwrap() {
+        EventLogEntry::TcgPcClientImrEvent(v) => {
+            assert_eq!(v.imr_index, 0);
+            assert_eq!(v.event_type, 0x3);
+            assert_eq!(v.digest, [9; 20]);
+            assert_eq!(v.event_size, 2);
+            assert_eq!(v.event, vec![1, 2]);
+        }
+        _ => panic!("Excepted a TcgPcClientImrEvent"),
+    }
+}
+
+#[test]
+fn malformed_records() {
+    let mut event = record(0, 0, 0x3, &[], &[]);
+    assert!(
+        to_event_log_entry(event.clone()).is_err(),
+        "Excepted an error"
+    );
+
+    event
+        .extra_info
+        .insert(EVENT_FORMAT_KEY.to_string(), PCCLIENT_FORMAT.to_string());
+    event.digests.push(TcgDigest {
+        algo_id: TPM_ALG_SHA256.into(),
+        hash: vec![0; 32],
+    });
+    assert!(
+        to_event_log_entry(event.clone()).is_err(),
+        "Excepted an error"
+    );
+
+    event.extra_info.clear();
+    event.digests[0].algo_id = u32::MAX;
+    assert!(to_event_log_entry(event).is_err(), "Excepted an error");
+
+    let sha1 = record(0, 0, 0, &[], &[(TPM_ALG_SHA1, 0, 20)]);
+    assert!(matches!(
+        to_event_log_entry(sha1),
+        Ok(EventLogEntry::TcgImrEvent(_))
+    ));
+}
+
+#[test]
+fn cel_records_round_trip() {
+    let data = ima_event();
+    let event_logs = vec![
+        record(
+            5,
+            0,
+            EV_NO_ACTION,
+            b"Spec ID Event03\0",
+            &[(TPM_ALG_SHA1, 3, 20)],
+        ),
+        record(
+            6,
+            1,
+            0x80000001,
+            &[4, 5, 6],
+            &[(TPM_ALG_SHA256, 1, 32), (TPM_ALG_SHA384, 2, 48)],
+        ),
+        record(
+            7,
+            3,
+            IMA_MEASUREMENT_EVENT,
+            &data,
+            &[(TPM_ALG_SHA384, 7, 48)],
+        ),
+    ];
+
+    for (format, encoded) in [
+        (
+            EventlogFormat::CelJson,
+            cel::to_json(&event_logs, 5).unwrap(),
+        ),
+        (
+            EventlogFormat::CelCbor,
+            cel::to_cbor(&event_logs, 5).unwrap(),
+        ),
+    ] {
+        let records = decode_cel(&encoded, format).unwrap();
+        assert_eq!(records.len(), event_logs.len());
+        for (r, expected) in records.iter().zip(event_logs.iter()) {
+            assert!(matches!(
+                cel_to_event_log_entry(r),
+                EventLogEntry::TcgCanonicalEvent(_)
+            ));
+            assert_eq!(&cel_to_tcg_eventlog(r).unwrap(), expected);
+        }
+    }
+
+    let encoded = cel::to_json(&event_logs, 5).unwrap();
+    assert!(
+        decode_cel(&encoded, EventlogFormat::Tcg).is_err(),
+        "Excepted an error"
+    );
+    assert!(
+        decode_cel(&encoded, EventlogFormat::CelCbor).is_err(),
+        "Excepted an error"
+    );
+}
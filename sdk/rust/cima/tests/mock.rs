@@ -0,0 +1,95 @@
+#![cfg(feature = "mock")]
+
+use cima::client::cima_server_pb::{EventlogFormat, IsolationMode, TcgDigest, TcgEventlog};
+use cima::error::CimaError;
+use cima::mock::{MockServer, MOCK_CONTAINER_ID};
+use evidence_api::api_data::ExtraArgs;
+use evidence_api::tcg::{IMA_MEASUREMENT_EVENT, TPM_ALG_SHA384};
+use tonic::Code;
+
+fn event(imr_index: u32, value: u8) -> TcgEventlog {
+    TcgEventlog {
+        imr_index,
+        event_type: IMA_MEASUREMENT_EVENT,
+        digests: vec![TcgDigest {
+            algo_id: TPM_ALG_SHA384.into(),
+            hash: vec![value; 48],
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn mock_serves_scripted_evidence() {
+    let server = MockServer::start().unwrap();
+    server.set_cc_report(vec![1, 2, 3]);
+    let mut client = server.client().build();
+
+    let report = client
+        .get_cc_report_from_server(Some("nonce".to_string()), None, ExtraArgs {})
+        .unwrap();
+    assert_eq!(report.cc_report, vec![1, 2, 3]);
+    assert!(!report.unfiltered);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GetCcReport");
+    assert_eq!(requests[0].container_id, MOCK_CONTAINER_ID);
+    assert_eq!(requests[0].nonce.as_deref(), Some("nonce"));
+
+    server.set_isolation_mode(IsolationMode::Shared, false);
+    let count = client.get_cc_measurement_count_from_server().unwrap();
+    assert_eq!(count.count, 4);
+    let info = client.get_server_info_from_server().unwrap();
+    assert_eq!(info.imr_indexes, vec![0, 1, 2, 3]);
+    assert!(!info.container_isolated);
+}
+
+#[test]
+fn mock_event_logs_replay() {
+    let server = MockServer::start().unwrap();
+    server.push_event(event(1, 1)).unwrap();
+    server.push_event(event(3, 2)).unwrap();
+    server.push_event(event(3, 3)).unwrap();
+    let mut client = server.client().build();
+
+    let event_logs = client
+        .get_cc_eventlog_from_server(Some(1), Some(5))
+        .unwrap();
+    assert_eq!(event_logs.event_logs.len(), 2);
+    assert_eq!(event_logs.event_logs[0].rec_num, 1);
+    assert!(client.get_cc_eventlog_from_server(Some(4), None).is_err());
+
+    let cel = client
+        .get_cc_eventlog_cel_from_server(None, None, EventlogFormat::CelJson)
+        .unwrap();
+    assert!(!cel.is_empty());
+
+    let report = client.verify_event_logs_from_server().unwrap();
+    assert!(report.verified());
+    assert_eq!(report.imrs[3].event_count, 2);
+
+    // Events missing from the log no longer replay to the measurement.
+    server.set_event_logs(vec![event(1, 1)]);
+    let report = client.verify_event_logs_from_server().unwrap();
+    assert_eq!(report.mismatched_imrs(), vec![3]);
+}
+
+#[test]
+fn mock_injected_errors() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.client().build();
+
+    server.set_error(Some((Code::PermissionDenied, "denied")));
+    assert_eq!(
+        client.get_cc_measurement_count_from_server().err(),
+        Some(CimaError::PermissionDenied("denied".to_string()))
+    );
+
+    server.set_error(None);
+    assert!(client.get_readiness_from_server().unwrap().ready);
+
+    let address = server.address().to_string();
+    drop(server);
+    assert!(!std::path::Path::new(&address).exists());
+}
@@ -0,0 +1,104 @@
+use cima::client::cima_server_pb::{TcgDigest, TcgEventlog};
+use cima::replay::{replay, replay_imr, CONTAINER_IMR};
+use evidence_api::tcg::{EV_NO_ACTION, IMA_MEASUREMENT_EVENT, TPM_ALG_SHA256, TPM_ALG_SHA384};
+use openssl::hash::{hash, MessageDigest};
+
+fn digest(algo_id: u16, value: u8) -> TcgDigest {
+    let size = if algo_id == TPM_ALG_SHA256 { 32 } else { 48 };
+    TcgDigest {
+        algo_id: algo_id.into(),
+        hash: vec![value; size],
+    }
+}
+
+fn event(imr_index: u32, event_type: u32, digests: Vec<TcgDigest>) -> TcgEventlog {
+    TcgEventlog {
+        imr_index,
+        event_type,
+        digests,
+        ..Default::default()
+    }
+}
+
+fn extend(imr: &[u8], digest: &TcgDigest) -> Vec<u8> {
+    hash(MessageDigest::sha384(), &[imr, &digest.hash].concat())
+        .unwrap()
+        .to_vec()
+}
+
+fn sha384(hash: Vec<u8>) -> TcgDigest {
+    TcgDigest {
+        algo_id: TPM_ALG_SHA384.into(),
+        hash,
+    }
+}
+
+#[test]
+fn replay_tcg_imr() {
+    let events = vec![
+        event(1, EV_NO_ACTION, vec![digest(TPM_ALG_SHA384, 9)]),
+        event(
+            1,
+            0x80000001,
+            vec![digest(TPM_ALG_SHA256, 1), digest(TPM_ALG_SHA384, 2)],
+        ),
+        event(0, 0x80000001, vec![digest(TPM_ALG_SHA384, 3)]),
+        event(1, 0x80000002, vec![digest(TPM_ALG_SHA384, 4)]),
+    ];
+    let imr = extend(&[0; 48], &digest(TPM_ALG_SHA384, 2));
+    let imr = extend(&imr, &digest(TPM_ALG_SHA384, 4));
+
+    let result = replay_imr(1, &sha384(imr.clone()), &events, true).unwrap();
+    assert_eq!(result.event_count, 2);
+    assert_eq!(result.replayed, imr);
+    assert_eq!(result.matched_events, Some(2));
+    assert!(result.matches());
+}
+
+#[test]
+fn replay_container_imr() {
+    // The container IMR is extended with every digest of an event.
+    let events = vec![event(
+        CONTAINER_IMR,
+        IMA_MEASUREMENT_EVENT,
+        vec![digest(TPM_ALG_SHA384, 5), digest(TPM_ALG_SHA384, 6)],
+    )];
+    let imr = extend(&[0; 48], &digest(TPM_ALG_SHA384, 5));
+    let imr = extend(&imr, &digest(TPM_ALG_SHA384, 6));
+
+    let measurements = vec![(CONTAINER_IMR, sha384(imr))];
+    let report = replay(&measurements, &events, true).unwrap();
+    assert!(report.verified());
+    assert!(!report.unfiltered);
+
+    // The TCG IMR 3 of an unfiltered log is only extended with the first digest.
+    let report = replay(&measurements, &events, false).unwrap();
+    assert!(!report.verified());
+    assert_eq!(report.mismatched_imrs(), vec![CONTAINER_IMR]);
+}
+
+#[test]
+fn replay_extended_after_measurement() {
+    let events = vec![
+        event(2, IMA_MEASUREMENT_EVENT, vec![digest(TPM_ALG_SHA384, 7)]),
+        event(2, IMA_MEASUREMENT_EVENT, vec![digest(TPM_ALG_SHA384, 8)]),
+    ];
+    let imr = extend(&[0; 48], &digest(TPM_ALG_SHA384, 7));
+
+    let result = replay_imr(2, &sha384(imr), &events, false).unwrap();
+    assert_eq!(result.event_count, 2);
+    assert_eq!(result.matched_events, Some(1));
+
+    let result = replay_imr(2, &sha384(vec![1; 48]), &events, false).unwrap();
+    assert_eq!(result.matched_events, None);
+
+    let mismatched = vec![event(
+        CONTAINER_IMR,
+        IMA_MEASUREMENT_EVENT,
+        vec![digest(TPM_ALG_SHA256, 1)],
+    )];
+    assert!(
+        replay_imr(CONTAINER_IMR, &sha384(vec![0; 48]), &mismatched, true).is_err(),
+        "Excepted an error"
+    );
+}
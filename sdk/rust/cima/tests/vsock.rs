@@ -0,0 +1,14 @@
+use cima::vsock::parse_address;
+use std::io::ErrorKind;
+
+#[test]
+fn vsock_address() {
+    assert_eq!(parse_address("vsock://3:4050").unwrap().unwrap(), (3, 4050));
+    // The unix domain socket paths are not vsock addresses.
+    assert!(parse_address("/run/cima/cima.sock").is_none());
+
+    for address in ["vsock://3", "vsock://3:port", "vsock://-1:4050", "vsock://"] {
+        let error = parse_address(address).unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
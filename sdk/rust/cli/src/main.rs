@@ -0,0 +1,466 @@
+use anyhow::{anyhow, Error};
+use cima::client::cima_server_pb::{EventlogFormat, TcgDigest, TcgEventlog};
+use cima::client::{CimaAsyncClient, ClientBuilder};
+use cima::replay::{self, ReplayReport};
+use clap::{Parser, Subcommand, ValueEnum};
+use evidence_api::tcg;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "cima-cli",
+    version,
+    about = "Query the evidence of the CIMA server"
+)]
+struct Cli {
+    /// UDS sock file or vsock://<cid>:<port> address [default: /run/cima/uds/cima-server.sock]
+    #[arg(short, long, env = "CIMA_SOCK")]
+    sock: Option<String>,
+    /// Container ID sent to the server, instead of the one discovered
+    #[arg(long)]
+    container_id: Option<String>,
+    /// Timeout of each request in seconds
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Print JSON for scripting instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch a CC report
+    Report {
+        /// Nonce in base64, given by the verifier
+        #[arg(long)]
+        nonce: Option<String>,
+        /// User data in base64, bound into the report
+        #[arg(long)]
+        user_data: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = ReportFormat::Base64)]
+        format: ReportFormat,
+        /// File to write the report to, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List the measurements of the IMRs visible to the container
+    Imr {
+        /// Only show this IMR
+        #[arg(short, long)]
+        index: Option<u8>,
+    },
+    /// Dump or export the event logs
+    Eventlog {
+        /// Index of the first event
+        #[arg(long)]
+        start: Option<u32>,
+        /// Maximum number of events
+        #[arg(long)]
+        count: Option<u32>,
+        #[arg(short, long, value_enum, default_value_t = EventlogOutput::Text)]
+        format: EventlogOutput,
+        /// File to write the event logs to, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Replay the event logs and verify them against the IMRs, exit with 2 on mismatch
+    Verify,
+    /// Query the server capabilities and readiness
+    Info,
+    /// Print the new events as they are measured
+    Watch {
+        /// Polling interval in seconds
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Print the events already measured first
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ReportFormat {
+    Raw,
+    Base64,
+    Hex,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum EventlogOutput {
+    Text,
+    Json,
+    CelJson,
+    CelCbor,
+    Ima,
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn algo_name(algo_id: u32) -> String {
+    match u16::try_from(algo_id) {
+        Ok(tcg::TPM_ALG_SHA1) => "sha1".to_string(),
+        Ok(tcg::TPM_ALG_SHA256) => "sha256".to_string(),
+        Ok(tcg::TPM_ALG_SHA384) => "sha384".to_string(),
+        Ok(tcg::TPM_ALG_SHA512) => "sha512".to_string(),
+        _ => format!("0x{:x}", algo_id),
+    }
+}
+
+fn digest_json(digest: &TcgDigest) -> Value {
+    json!({
+        "algo_id": digest.algo_id,
+        "algo": algo_name(digest.algo_id),
+        "hash": hex(&digest.hash),
+    })
+}
+
+fn event_json(event: &TcgEventlog) -> Value {
+    json!({
+        "rec_num": event.rec_num,
+        "imr_index": event.imr_index,
+        "event_type": event.event_type,
+        "digests": event.digests.iter().map(digest_json).collect::<Vec<_>>(),
+        "event_size": event.event_size,
+        "event": base64::encode(&event.event),
+        "extra_info": event.extra_info,
+    })
+}
+
+fn event_text(event: &TcgEventlog) -> String {
+    let mut text = format!(
+        "{:>6} IMR[{}] type 0x{:08x} size {}",
+        event.rec_num, event.imr_index, event.event_type, event.event_size
+    );
+    for digest in &event.digests {
+        text += &format!(
+            "\n       {}: {}",
+            algo_name(digest.algo_id),
+            hex(&digest.hash)
+        );
+    }
+    text
+}
+
+fn replay_json(report: &ReplayReport) -> Value {
+    let imrs: Vec<Value> = report
+        .imrs
+        .iter()
+        .map(|v| {
+            json!({
+                "index": v.index,
+                "algo": algo_name(v.algo_id),
+                "measurement": hex(&v.measurement),
+                "replayed": hex(&v.replayed),
+                "event_count": v.event_count,
+                "matched_events": v.matched_events,
+                "matches": v.matches(),
+            })
+        })
+        .collect();
+    json!({
+        "verified": report.verified(),
+        "unfiltered": report.unfiltered,
+        "imrs": imrs,
+    })
+}
+
+// Write to the file, or to stdout without one.
+fn write_output(output: &Option<PathBuf>, data: &[u8]) -> Result<(), Error> {
+    match output {
+        Some(path) => std::fs::write(path, data)?,
+        None => std::io::stdout().write_all(data)?,
+    }
+    Ok(())
+}
+
+fn print_json(value: &Value) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn report(
+    client: &CimaAsyncClient,
+    json: bool,
+    nonce: Option<String>,
+    user_data: Option<String>,
+    format: ReportFormat,
+    output: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let response = client.get_cc_report(nonce, user_data).await?;
+    if json {
+        let value = json!({
+            "cc_type": response.cc_type,
+            "cc_report": base64::encode(&response.cc_report),
+            "unfiltered": response.unfiltered,
+        });
+        let mut data = serde_json::to_vec_pretty(&value)?;
+        data.push(b'\n');
+        return write_output(output, &data);
+    }
+
+    match format {
+        ReportFormat::Raw => write_output(output, &response.cc_report),
+        ReportFormat::Base64 => write_output(
+            output,
+            format!("{}\n", base64::encode(&response.cc_report)).as_bytes(),
+        ),
+        ReportFormat::Hex => {
+            write_output(output, format!("{}\n", hex(&response.cc_report)).as_bytes())
+        }
+    }
+}
+
+async fn imr(client: &CimaAsyncClient, json: bool, index: Option<u8>) -> Result<(), Error> {
+    let algo_id = client.get_default_algorithm().await?.algo_id;
+    let indexes = match index {
+        Some(v) => vec![v],
+        None => client
+            .get_server_info()
+            .await?
+            .imr_indexes
+            .into_iter()
+            .map(u8::try_from)
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut imrs = vec![];
+    for index in indexes {
+        let response = client
+            .get_cc_measurement(index, algo_id.try_into()?)
+            .await?;
+        match response.measurement {
+            Some(v) => imrs.push((index, v)),
+            None => return Err(anyhow!("No measurement of IMR {}.", index)),
+        }
+    }
+
+    if json {
+        let imrs: Vec<Value> = imrs
+            .iter()
+            .map(|(index, digest)| {
+                let mut value = digest_json(digest);
+                value["index"] = json!(index);
+                value
+            })
+            .collect();
+        return print_json(&json!(imrs));
+    }
+    for (index, digest) in imrs {
+        println!(
+            "IMR[{}] {}: {}",
+            index,
+            algo_name(digest.algo_id),
+            hex(&digest.hash)
+        );
+    }
+    Ok(())
+}
+
+async fn eventlog(
+    client: &CimaAsyncClient,
+    start: Option<u32>,
+    count: Option<u32>,
+    format: EventlogOutput,
+    output: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let server_format = match format {
+        EventlogOutput::Text | EventlogOutput::Json => EventlogFormat::Tcg,
+        EventlogOutput::CelJson => EventlogFormat::CelJson,
+        EventlogOutput::CelCbor => EventlogFormat::CelCbor,
+        EventlogOutput::Ima => EventlogFormat::ImaAscii,
+    };
+    let response = client.get_cc_eventlog(start, count, server_format).await?;
+
+    match format {
+        EventlogOutput::Text => {
+            let mut text = String::new();
+            for event in &response.event_logs {
+                text += &event_text(event);
+                text.push('\n');
+            }
+            write_output(output, text.as_bytes())
+        }
+        EventlogOutput::Json => {
+            let value = json!({
+                "unfiltered": response.unfiltered,
+                "event_logs": response.event_logs.iter().map(event_json).collect::<Vec<_>>(),
+            });
+            let mut data = serde_json::to_vec_pretty(&value)?;
+            data.push(b'\n');
+            write_output(output, &data)
+        }
+        EventlogOutput::CelJson | EventlogOutput::CelCbor => {
+            write_output(output, &response.cel_event_logs)
+        }
+        EventlogOutput::Ima => write_output(output, response.ima_ascii_event_logs.as_bytes()),
+    }
+}
+
+// Print the replay of the IMRs, and whether they all match.
+async fn verify(client: &CimaAsyncClient, json: bool) -> Result<bool, Error> {
+    let report = replay::verify(client).await?;
+    if json {
+        print_json(&replay_json(&report))?;
+        return Ok(report.verified());
+    }
+
+    for imr in &report.imrs {
+        match imr.matched_events {
+            Some(v) => println!(
+                "IMR[{}] {}: OK, {} of {} events replayed",
+                imr.index,
+                algo_name(imr.algo_id),
+                v,
+                imr.event_count
+            ),
+            None => println!(
+                "IMR[{}] {}: MISMATCH\n  measurement: {}\n  replayed:    {}",
+                imr.index,
+                algo_name(imr.algo_id),
+                hex(&imr.measurement),
+                hex(&imr.replayed)
+            ),
+        }
+    }
+    if report.unfiltered {
+        println!("The event logs are not scoped to the container.");
+    }
+    Ok(report.verified())
+}
+
+async fn info(client: &CimaAsyncClient, json: bool) -> Result<(), Error> {
+    let info = client.get_server_info().await?;
+    let readiness = client.get_readiness().await?;
+    let checks: Vec<Value> = readiness
+        .checks
+        .iter()
+        .map(|v| json!({"name": v.name, "ok": v.ok, "reason": v.reason}))
+        .collect();
+    let algos: Vec<String> = info.algo_ids.iter().map(|v| algo_name(*v)).collect();
+
+    if json {
+        return print_json(&json!({
+            "version": info.version,
+            "backend": info.backend,
+            "cc_type": info.cc_type,
+            "ima_enabled": info.ima_enabled,
+            "container_isolated": info.container_isolated,
+            "isolation_mode": info.isolation_mode().as_str_name(),
+            "algos": algos,
+            "imr_indexes": info.imr_indexes,
+            "ready": readiness.ready,
+            "checks": checks,
+        }));
+    }
+
+    println!("version:            {}", info.version);
+    println!("backend:            {}", info.backend);
+    println!("cc type:            {}", info.cc_type);
+    println!("IMA enabled:        {}", info.ima_enabled);
+    println!("container isolated: {}", info.container_isolated);
+    println!(
+        "isolation mode:     {}",
+        info.isolation_mode().as_str_name()
+    );
+    println!("algorithms:         {}", algos.join(", "));
+    println!("IMR indexes:        {:?}", info.imr_indexes);
+    println!("ready:              {}", readiness.ready);
+    for check in &readiness.checks {
+        match check.ok {
+            true => println!("  {}: ok", check.name),
+            false => println!("  {}: {}", check.name, check.reason),
+        }
+    }
+    Ok(())
+}
+
+// Poll the event logs and print the new events, one JSON object per line with --json.
+async fn watch(
+    client: &CimaAsyncClient,
+    json: bool,
+    interval: Duration,
+    all: bool,
+) -> Result<(), Error> {
+    let mut start = match all {
+        true => 0,
+        false => client
+            .get_cc_eventlog(None, None, EventlogFormat::Tcg)
+            .await?
+            .event_logs
+            .len() as u32,
+    };
+
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let response = client
+            .get_cc_eventlog(Some(start), None, EventlogFormat::Tcg)
+            .await?;
+        for event in &response.event_logs {
+            match json {
+                true => println!("{}", event_json(event)),
+                false => println!("{}", event_text(event)),
+            }
+        }
+        std::io::stdout().flush()?;
+        start += response.event_logs.len() as u32;
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+
+    let mut builder = ClientBuilder::new();
+    if let Some(v) = &cli.sock {
+        builder = builder.address(v);
+    }
+    if let Some(v) = &cli.container_id {
+        builder = builder.container_id(v);
+    }
+    if let Some(v) = cli.timeout {
+        builder = builder
+            .connect_timeout(Duration::from_secs(v))
+            .request_timeout(Duration::from_secs(v));
+    }
+    let client = builder.connect().await?;
+
+    match cli.command {
+        Command::Report {
+            nonce,
+            user_data,
+            format,
+            output,
+        } => report(&client, cli.json, nonce, user_data, format, &output).await,
+        Command::Imr { index } => imr(&client, cli.json, index).await,
+        Command::Eventlog {
+            start,
+            count,
+            format,
+            output,
+        } => {
+            let format = match (cli.json, format) {
+                (true, EventlogOutput::Text) => EventlogOutput::Json,
+                (_, v) => v,
+            };
+            eventlog(&client, start, count, format, &output).await
+        }
+        Command::Verify => {
+            if !verify(&client, cli.json).await? {
+                std::process::exit(2);
+            }
+            Ok(())
+        }
+        Command::Info => info(&client, cli.json).await,
+        Command::Watch { interval, all } => {
+            watch(&client, cli.json, Duration::from_secs(interval), all).await
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use cima::client::cima_server_pb::{TcgDigest, TcgEventlog};
+use cima::mock::MockServer;
+use evidence_api::tcg::{IMA_MEASUREMENT_EVENT, TPM_ALG_SHA384};
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn cima_cli(server: &MockServer, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cima-cli"))
+        .args(["--sock", server.address(), "--container-id", "cima-cli"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn event(imr_index: u32, value: u8) -> TcgEventlog {
+    TcgEventlog {
+        imr_index,
+        event_type: IMA_MEASUREMENT_EVENT,
+        digests: vec![TcgDigest {
+            algo_id: TPM_ALG_SHA384.into(),
+            hash: vec![value; 48],
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cli_report_formats() {
+    let server = MockServer::start().unwrap();
+    server.set_cc_report(vec![0xde, 0xad, 0xbe, 0xef]);
+
+    let output = cima_cli(&server, &["report", "--format", "hex"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"deadbeef\n");
+
+    let output = cima_cli(&server, &["report", "--format", "raw"]);
+    assert_eq!(output.stdout, vec![0xde, 0xad, 0xbe, 0xef]);
+
+    let output = cima_cli(&server, &["--json", "report", "--nonce", "bm9uY2U="]);
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["cc_report"], "3q2+7w==");
+    assert_eq!(value["cc_type"], 1);
+
+    let requests = server.requests();
+    assert_eq!(requests.last().unwrap().nonce.as_deref(), Some("bm9uY2U="));
+    assert_eq!(requests.last().unwrap().container_id, "cima-cli");
+
+    let path = std::env::temp_dir().join(format!("cima-cli-report-{}.json", std::process::id()));
+    let output = cima_cli(&server, &["--json", "report", "-o", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let value: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(value["cc_report"], "3q2+7w==");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cli_imrs_and_event_logs() {
+    let server = MockServer::start().unwrap();
+    server.push_event(event(3, 1)).unwrap();
+    server.push_event(event(3, 2)).unwrap();
+
+    let output = cima_cli(&server, &["--json", "imr"]);
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value.as_array().unwrap().len(), 4);
+    assert_eq!(value[3]["index"], 3);
+    assert_eq!(value[3]["algo"], "sha384");
+
+    let output = cima_cli(&server, &["eventlog", "--start", "1"]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("     1 IMR[3] type 0x00000014"));
+    assert!(text.contains(&format!("sha384: {}", "02".repeat(48))));
+
+    let output = cima_cli(&server, &["--json", "eventlog"]);
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["event_logs"].as_array().unwrap().len(), 2);
+    assert_eq!(value["unfiltered"], false);
+
+    let output = cima_cli(&server, &["eventlog", "--format", "cel-json"]);
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn cli_verify() {
+    let server = MockServer::start().unwrap();
+    server.push_event(event(3, 1)).unwrap();
+
+    let output = cima_cli(&server, &["--json", "verify"]);
+    assert!(output.status.success());
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["verified"], true);
+
+    server.set_event_logs(vec![]);
+    let output = cima_cli(&server, &["verify"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("IMR[3] sha384: MISMATCH"));
+}
+
+#[test]
+fn cli_info() {
+    let server = MockServer::start().unwrap();
+    server.set_ready(false);
+
+    let output = cima_cli(&server, &["--json", "info"]);
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["backend"], "mock");
+    assert_eq!(value["isolation_mode"], "ISOLATION_MODE_ISOLATED");
+    assert_eq!(value["algos"][0], "sha384");
+    assert_eq!(value["ready"], false);
+}
+
+#[test]
+fn cli_watch() {
+    let server = MockServer::start().unwrap();
+    server.push_event(event(3, 1)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cima-cli"))
+        .args(["--sock", server.address(), "--container-id", "cima-cli"])
+        .args(["--json", "watch", "--interval", "1", "--all"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The lines are read on a thread, so a missing one fails the test instead of hanging it.
+    let stdout = child.stdout.take().unwrap();
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let next_line = || -> Value {
+        let line = lines.recv_timeout(Duration::from_secs(10)).unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+
+    let first = next_line();
+    // The events measured later are printed by the next polls.
+    server.push_event(event(3, 2)).unwrap();
+    let next = next_line();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(first["rec_num"], 0);
+    assert_eq!(next["rec_num"], 1);
+    assert_eq!(next["digests"][0]["hash"], "02".repeat(48));
+
+    let output = cima_cli(&server, &["watch", "--interval", "0"]);
+    assert_eq!(output.status.code(), Some(2));
+}
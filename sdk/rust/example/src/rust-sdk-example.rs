@@ -200,6 +200,23 @@ fn get_cc_eventlog() {
     for replay_result in replay_results {
         replay_result.show();
     }
+
+    // replay the eventlog against the measurements, with the container IMR of CIMA
+    info!("call [verify_event_logs_from_server] to verify container related eventlog!");
+    let report = match API::client().verify_event_logs_from_server() {
+        Ok(r) => r,
+        Err(e) => {
+            error!("error verify eventlog: {:?}", e);
+            std::process::exit(-1);
+        }
+    };
+    for imr in &report.imrs {
+        info!(
+            "IMR {}: {} events, matched = {:?}",
+            imr.index, imr.event_count, imr.matched_events
+        );
+    }
+    info!("event logs verified: {}", report.verified());
 }
 
 fn main() {
@@ -1,19 +1,72 @@
 use anyhow::{anyhow, Error};
 use cctrusted_vm::sdk::API;
-use evidence_api::{api::EvidenceApi, api_data::ExtraArgs, tcg};
-use log::info;
+use evidence_api::{api::EvidenceApi, api_data::ExtraArgs, cc_type::TeeType, tcg};
+use log::{info, warn};
+use openssl::hash::{hash, MessageDigest};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::read_to_string;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tonic::Status;
 
 use crate::{
-    cima_pb::{TcgDigest, TcgEventlog},
+    binding::{binding_digest, new_binding},
+    cache::{ReportCache, ReportKey},
+    cima_pb::{
+        ExtendContainerMeasurementResponse, GetCcReportResponse, GetEvidenceResponse,
+        GetReadinessResponse, GetServerInfoResponse, ImrMeasurement, IsolationMode, ReadinessCheck,
+        ReportBinding, TcgDigest, TcgEventlog,
+    },
     container::Container,
+    eventlog,
     measurement::Measurement,
+    metrics,
     policy::PolicyConfig,
+    ratelimit::{self, RateLimiter},
+    report_data::{self, MAX_NONCE_SIZE, MAX_USER_DATA_SIZE},
 };
 
 pub const IMA_PATTERN: &str = "ima_template=ima-cgpath";
+pub const MAX_APPLICATION_EVENT_NAME: usize = 255;
+pub const MAX_APPLICATION_EVENT_SIZE: usize = 64 * 1024;
+// Reports generated for an evidence before giving up when the measurements keep changing.
+pub const EVIDENCE_ATTEMPTS: usize = 3;
+
+// The device nodes of the TEEs, in the order evidence_api looks for them, a vTPM only
+// counts when the VM has no other TEE.
+const TEE_DEVICES: [(&str, TeeType); 4] = [
+    ("/dev/tdx_guest", TeeType::TDX),
+    ("/dev/tdx-guest", TeeType::TDX),
+    ("/dev/sev-guest", TeeType::SEV),
+    ("/dev/tpmrm0", TeeType::TPM),
+];
+
+// The nonce and the user data passed to the backend, and the binding they are made of.
+type ReportInput = (Option<String>, Option<String>, Option<ReportBinding>);
+
+/*
+ * Produce a value between two reads of the state, again while the state changes meanwhile,
+ * so the value is taken at the state returned with it.
+ */
+pub fn consistent<C, S: PartialEq, T>(
+    ctx: &mut C,
+    attempts: usize,
+    mut state: impl FnMut(&mut C) -> Result<S, Error>,
+    mut produce: impl FnMut(&mut C) -> Result<T, Error>,
+) -> Result<(S, T), Error> {
+    let mut before = state(ctx)?;
+    for _ in 0..attempts {
+        let value = produce(ctx)?;
+        let after = state(ctx)?;
+        if after == before {
+            return Ok((after, value));
+        }
+        before = after;
+    }
+
+    Err(Status::aborted("The measurements keep changing, retry later.").into())
+}
 
 pub enum IMR {
     FIRMWARE = 0,
@@ -22,11 +75,49 @@ pub enum IMR {
     CONTAINER = 3,
 }
 
+// The view of the evidence a caller gets.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Scope {
+    // The evidence of the container, filtered according to the isolation mode.
+    Container(String),
+    // The evidence of the whole node, for trusted node level verifiers.
+    System,
+}
+
 pub struct Agent {
     measurement: Option<Measurement>,
     containers: HashMap<String, Container>,
     event_logs: Vec<TcgEventlog>,
+    // The number of the events of the backend event log handled, skipped ones included.
+    backend_offset: u32,
     ima_enabled: bool,
+    mode: IsolationMode,
+    backend: String,
+    cc_type: TeeType,
+    application_enabled: bool,
+    application_limiter: RateLimiter,
+    report_limiter: Option<RateLimiter>,
+    report_cache: Option<ReportCache>,
+    initialized: bool,
+    last_ingestion: Option<Instant>,
+    replays: Vec<Replay>,
+    // The algorithms of the digests in the event logs.
+    event_log_algo_ids: BTreeSet<u32>,
+}
+
+/*
+ * The replay of the event logs of an IMR, kept at the last prefix of the event logs
+ * which replayed to its measurement. The measurement only moves forward, so the next
+ * check replays the events from there.
+ */
+#[derive(Clone, Default)]
+struct Replay {
+    algo_id: u32,
+    imr: Vec<u8>,
+    // The position in the event logs after the last replayed event.
+    position: usize,
+    // The number of the events of the IMR up to the position.
+    events: usize,
 }
 
 impl Default for Agent {
@@ -41,16 +132,72 @@ impl Agent {
             measurement: None,
             containers: HashMap::new(),
             event_logs: vec![],
+            backend_offset: 0,
             ima_enabled: false,
+            mode: IsolationMode::Unspecified,
+            backend: String::new(),
+            cc_type: TeeType::PLAIN,
+            application_enabled: false,
+            application_limiter: RateLimiter::new(0, 0),
+            report_limiter: None,
+            report_cache: None,
+            initialized: false,
+            last_ingestion: None,
+            replays: vec![],
+            event_log_algo_ids: BTreeSet::new(),
         }
     }
 
+    fn detect_tee_type() -> TeeType {
+        for (device, tee_type) in TEE_DEVICES {
+            if Path::new(device).exists() {
+                return tee_type;
+            }
+        }
+        TeeType::PLAIN
+    }
+
     pub fn init(&mut self, policy: PolicyConfig) -> Result<(), Error> {
         let cmdline = read_to_string("/proc/cmdline").expect("Failed to read /proc/cmdline.");
+        let isolated = policy.container_isolated().unwrap_or(false);
+        self.backend = policy.backend().cloned().unwrap_or_default();
+        self.cc_type = Agent::detect_tee_type();
+        self.application_enabled = policy.container_application_enabled().unwrap_or(false);
+        self.application_limiter = RateLimiter::new(
+            policy.container_application_burst().unwrap_or(10),
+            policy.container_application_rate().unwrap_or(60),
+        );
+        self.report_limiter = match (policy.report_burst(), policy.report_rate()) {
+            (None, None) => None,
+            (burst, rate) => Some(RateLimiter::new(burst.unwrap_or(10), rate.unwrap_or(60))),
+        };
+        self.report_cache = match policy.report_cache_ttl() {
+            Some(v) if v > 0 => Some(ReportCache::new(Duration::from_secs(v.into()))),
+            _ => None,
+        };
         if !cmdline.contains(IMA_PATTERN) {
             self.ima_enabled = false;
+            self.mode = if !isolated {
+                IsolationMode::Shared
+            } else {
+                match policy.container_ima_unavailable().map(|v| v.as_str()) {
+                    Some("refuse") | None => IsolationMode::Refuse,
+                    Some("firmwareOnly") => IsolationMode::FirmwareOnly,
+                    Some("unfiltered") => IsolationMode::Unfiltered,
+                    Some(v) => return Err(anyhow!("Unknown imaUnavailable policy: {}", v)),
+                }
+            };
+            info!(
+                "IMA is not enabled, serving container evidence in {} mode.",
+                self.mode.as_str_name()
+            );
         } else {
             self.ima_enabled = true;
+            self.mode = if isolated {
+                IsolationMode::Isolated
+            } else {
+                IsolationMode::Shared
+            };
             // Measure the system when Agent initialization
             self.measurement = Some(Measurement::new(policy));
             match self
@@ -64,13 +211,94 @@ impl Agent {
             }
         }
 
-        self.fetch_all_event_logs()
+        self.fetch_all_event_logs()?;
+        self.initialized = true;
+        Ok(())
+    }
+
+    pub fn get_isolation_mode(&self) -> (IsolationMode, bool) {
+        (self.mode, self.ima_enabled)
+    }
+
+    pub fn get_server_info(&mut self, scope: Scope) -> Result<GetServerInfoResponse, Error> {
+        let default_algo_id = self.get_default_algorithm()?;
+        let algo_ids = self.algo_ids(default_algo_id);
+        let imr_indexes = self.visible_imr_indexes(&scope)?;
+
+        Ok(GetServerInfoResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: self.backend.clone(),
+            cc_type: self.cc_type.clone() as i32,
+            ima_enabled: self.ima_enabled,
+            container_isolated: self.mode == IsolationMode::Isolated,
+            isolation_mode: self.mode.into(),
+            algo_ids,
+            imr_indexes,
+        })
+    }
+
+    // The default algorithm of the backend, then the other ones of the event log digests.
+    pub fn algo_ids(&self, default_algo_id: u32) -> Vec<u32> {
+        let mut algo_ids = vec![default_algo_id];
+        algo_ids.extend(
+            self.event_log_algo_ids
+                .iter()
+                .filter(|&&v| v != default_algo_id),
+        );
+        algo_ids
+    }
+
+    fn visible_imr_indexes(&self, scope: &Scope) -> Result<Vec<u32>, Error> {
+        let indexes = match (scope, self.mode) {
+            (Scope::Container(_), IsolationMode::Isolated) => vec![
+                IMR::FIRMWARE as u32,
+                IMR::KERNEL as u32,
+                IMR::CONTAINER as u32,
+            ],
+            (Scope::Container(_), IsolationMode::FirmwareOnly) => {
+                vec![IMR::FIRMWARE as u32, IMR::KERNEL as u32]
+            }
+            (Scope::Container(_), IsolationMode::Refuse) => vec![],
+            _ => (0..API::get_measurement_count()?.into()).collect(),
+        };
+
+        Ok(indexes)
+    }
+
+    // Whether the event logs and measurements served are not scoped to the caller container.
+    pub fn unfiltered(&self, scope: &Scope) -> bool {
+        *scope == Scope::System
+            || self.mode == IsolationMode::Shared
+            || self.mode == IsolationMode::Unfiltered
+    }
+
+    // Whether the CC report served is not bound to the caller container.
+    fn report_unbound(&self, scope: &Scope) -> bool {
+        *scope == Scope::System || self.mode != IsolationMode::Isolated
+    }
+
+    fn ima_unavailable() -> Error {
+        Status::failed_precondition(
+            "IMA is not enabled, container evidence is refused according to the policy.",
+        )
+        .into()
+    }
+
+    fn firmware_event_logs(&self) -> Vec<TcgEventlog> {
+        self.event_logs
+            .iter()
+            .filter(|e| e.imr_index == IMR::FIRMWARE as u32 || e.imr_index == IMR::KERNEL as u32)
+            .cloned()
+            .collect()
     }
 
     pub fn get_default_algorithm(&mut self) -> Result<u32, Error> {
         let algo = match API::get_default_algorithm() {
             Ok(v) => v,
-            Err(e) => return Err(e),
+            Err(e) => {
+                metrics::backend_error("get_default_algorithm");
+                return Err(e);
+            }
         };
 
         Ok(algo.algo_id.into())
@@ -79,83 +307,76 @@ impl Agent {
     pub fn get_measurement_count(&mut self) -> Result<u32, Error> {
         let count = match API::get_measurement_count() {
             Ok(v) => v,
-            Err(e) => return Err(e),
+            Err(e) => {
+                metrics::backend_error("get_measurement_count");
+                return Err(e);
+            }
         };
 
         Ok(count.into())
     }
 
     fn fetch_all_event_logs(&mut self) -> Result<(), Error> {
-        let start: u32 = self.event_logs.len() as u32;
-
-        let entries = match API::get_cc_eventlog(Some(start), None) {
+        let entries = match API::get_cc_eventlog(Some(self.backend_offset), None) {
             Ok(v) => v,
-            Err(e) => return Err(e),
+            Err(e) => {
+                metrics::backend_error("get_cc_eventlog");
+                return Err(e);
+            }
         };
+        metrics::ingested();
+        self.last_ingestion = Some(Instant::now());
 
+        self.ingest_event_logs(entries)
+    }
+
+    // Add the events fetched from the backend event log after the backend offset.
+    pub fn ingest_event_logs(&mut self, entries: Vec<tcg::EventLogEntry>) -> Result<(), Error> {
         if entries.is_empty() {
             return Ok(());
         }
 
         for entry in entries {
-            match entry {
-                tcg::EventLogEntry::TcgImrEvent(event) => {
-                    let mut digests: Vec<TcgDigest> = vec![];
-                    for d in event.digests {
-                        digests.push(TcgDigest {
-                            algo_id: d.algo_id as u32,
-                            hash: d.hash,
-                        })
-                    }
-                    let tcg_event = TcgEventlog {
-                        rec_num: 0,
-                        imr_index: event.imr_index,
-                        event_type: event.event_type,
-                        event_size: event.event_size,
-                        event: event.event,
-                        digests,
-                        extra_info: HashMap::new(),
-                    };
-
-                    if tcg_event.event_type == tcg::IMA_MEASUREMENT_EVENT {
-                        match self.filter_container(tcg_event.clone()) {
-                            Ok(_v) => _v,
-                            Err(e) => return Err(e),
-                        }
-                    }
-
-                    self.event_logs.push(tcg_event)
-                }
-                tcg::EventLogEntry::TcgPcClientImrEvent(event) => {
-                    let mut digests: Vec<TcgDigest> = vec![];
-                    let algo_id = tcg::TcgDigest::get_algorithm_id_from_digest_size(
-                        event.digest.len().try_into().unwrap(),
-                    );
-
-                    digests.push(TcgDigest {
-                        algo_id: algo_id.into(),
-                        hash: event.digest.to_vec(),
-                    });
-                    self.event_logs.push(TcgEventlog {
-                        rec_num: 0,
-                        imr_index: event.imr_index,
-                        event_type: event.event_type,
-                        event_size: event.event_size,
-                        event: event.event,
-                        digests,
-                        extra_info: HashMap::new(),
-                    })
+            let tcg_event = match eventlog::to_tcg_eventlog(entry) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Skip an event of the backend event log: {:?}", e);
+                    // Not fetched again, the event logs are behind the backend offset now.
+                    self.backend_offset += 1;
+                    continue;
                 }
-                tcg::EventLogEntry::TcgCanonicalEvent(_event) => {
-                    todo!();
+            };
+
+            if tcg_event.event_type == tcg::IMA_MEASUREMENT_EVENT {
+                metrics::IMA_EVENTS.inc();
+                match self.filter_container(tcg_event.clone()) {
+                    Ok(_v) => _v,
+                    Err(e) => return Err(e),
                 }
             }
+
+            // The digests of the EV_NO_ACTION events, like the Spec ID event, are not extended.
+            if tcg_event.event_type != tcg::EV_NO_ACTION {
+                self.event_log_algo_ids
+                    .extend(tcg_event.digests.iter().map(|d| d.algo_id));
+            }
+            self.event_logs.push(tcg_event);
+            self.backend_offset += 1;
         }
         info!("Loaded {} event logs.", self.event_logs.len());
+        metrics::EVENTLOG_ENTRIES.set(self.event_logs.len() as i64);
 
         Ok(())
     }
 
+    pub fn event_log_count(&self) -> usize {
+        self.event_logs.len()
+    }
+
+    pub fn backend_offset(&self) -> u32 {
+        self.backend_offset
+    }
+
     fn filter_container(&mut self, event: TcgEventlog) -> Result<(), Error> {
         let data = match String::from_utf8(event.event.to_vec()) {
             Ok(v) => v,
@@ -167,76 +388,132 @@ impl Agent {
             return Ok(());
         }
 
-        if cgpath[1].contains("kubepods.slice") || cgpath[1].contains("system.slice/docker-") {
+        if Container::is_container_path(cgpath[1]) {
             let container_id = match Container::parse_id(cgpath) {
                 Ok(v) => v,
                 Err(e) => return Err(e),
             };
 
-            if !self.containers.contains_key(&container_id) {
-                let measurement = match self.measurement.as_mut() {
-                    Some(v) => v,
-                    None => return Err(anyhow!("The measurement was not initialized.")),
-                };
-
-                let mut container =
-                    Container::new(measurement.imr().clone(), measurement.event_logs().to_vec());
-                match container.extend_imr(IMR::CONTAINER as u32, event.clone()) {
-                    Ok(_v) => {
-                        self.containers.insert(container_id.clone(), container);
-                        return Ok(());
-                    }
-                    Err(e) => return Err(e),
-                }
-            } else {
-                let container = match self.containers.get_mut(&container_id) {
-                    Some(v) => v,
-                    None => return Err(anyhow!("Cannot get container as mutable.")),
-                };
-                return container.extend_imr(IMR::CONTAINER as u32, event.clone());
-            }
+            let container = self.container_mut(&container_id)?;
+            return container.extend_imr(IMR::CONTAINER as u32, event.clone());
         }
 
         Ok(())
     }
 
-    pub fn get_cc_eventlog(
+    // Get the container, which starts from the system measurement when first seen.
+    fn container_mut(&mut self, container_id: &str) -> Result<&mut Container, Error> {
+        if !self.containers.contains_key(container_id) {
+            let measurement = match self.measurement.as_ref() {
+                Some(v) => v,
+                None => return Err(anyhow!("The measurement was not initialized.")),
+            };
+
+            let container =
+                Container::new(measurement.imr().clone(), measurement.event_logs().to_vec());
+            self.containers.insert(container_id.to_string(), container);
+            metrics::CONTAINERS.set(self.containers.len() as i64);
+        }
+
+        match self.containers.get_mut(container_id) {
+            Some(v) => Ok(v),
+            None => Err(anyhow!("Cannot get container as mutable.")),
+        }
+    }
+
+    pub fn extend_container_measurement(
         &mut self,
         container_id: String,
-        start: Option<u32>,
-        count: Option<u32>,
-    ) -> Result<Vec<TcgEventlog>, Error> {
+        name: String,
+        event: Vec<u8>,
+        pid: Option<i32>,
+    ) -> Result<ExtendContainerMeasurementResponse, Error> {
+        match self.mode {
+            IsolationMode::Isolated => {}
+            IsolationMode::Refuse => return Err(Agent::ima_unavailable()),
+            _ => {
+                return Err(Status::failed_precondition(
+                    "Container measurement can only be extended when containers are isolated.",
+                )
+                .into())
+            }
+        }
+
+        if !self.application_enabled {
+            return Err(Status::permission_denied(
+                "Application measurement is disabled according to the policy.",
+            )
+            .into());
+        }
+
+        if name.is_empty() || name.len() > MAX_APPLICATION_EVENT_NAME || name.contains('\0') {
+            return Err(Status::invalid_argument(format!(
+                "Event name must be 1 to {} bytes without NUL.",
+                MAX_APPLICATION_EVENT_NAME
+            ))
+            .into());
+        }
+
+        if event.len() > MAX_APPLICATION_EVENT_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Event is larger than the maximum {} bytes.",
+                MAX_APPLICATION_EVENT_SIZE
+            ))
+            .into());
+        }
+
+        if let Err(retry_after) = self.application_limiter.take(&container_id) {
+            return Err(ratelimit::exhausted("Container measurement", retry_after).into());
+        }
+
+        // Keep the IMA events measured before this one in order.
         let _ = self.fetch_all_event_logs();
-        let mut event_logs = vec![];
 
-        if self.ima_enabled {
-            let measurement = match self.measurement.as_mut() {
-                Some(v) => v,
-                None => return Err(anyhow!("The measurement was not initialized.")),
-            };
+        let container = self.container_mut(&container_id)?;
+        container.extend_application_event(&name, &event, pid)?;
+        info!(
+            "Extended application event {} of container {}.",
+            name, container_id
+        );
+
+        Ok(ExtendContainerMeasurementResponse {
+            measurement: Some(container.imr().clone()),
+            event_count: container.event_logs().len() as u32,
+        })
+    }
 
-            if measurement.container_isolated() {
-                if !self.containers.contains_key(&container_id) {
-                    return Err(anyhow!("Container cannot be found."));
-                }
+    fn scoped_event_logs(&self, scope: &Scope) -> Result<Vec<TcgEventlog>, Error> {
+        let mut event_logs = vec![];
 
-                for event_log in &self.event_logs {
-                    if event_log.imr_index == IMR::FIRMWARE as u32
-                        || event_log.imr_index == IMR::KERNEL as u32
-                    {
-                        event_logs.push(event_log.clone());
-                    }
+        match (scope, self.mode) {
+            (Scope::Container(container_id), IsolationMode::Isolated) => {
+                if !self.containers.contains_key(container_id) {
+                    return Err(anyhow!("Container cannot be found."));
                 }
 
-                let container = &self.containers[&container_id];
+                event_logs.extend(self.firmware_event_logs());
+                let container = &self.containers[container_id];
                 event_logs.extend(container.event_logs().clone());
-            } else {
-                event_logs.extend(self.event_logs.to_vec());
             }
-        } else {
-            event_logs.extend(self.event_logs.to_vec());
+            (Scope::Container(_), IsolationMode::FirmwareOnly) => {
+                event_logs.extend(self.firmware_event_logs())
+            }
+            (Scope::Container(_), IsolationMode::Refuse) => return Err(Agent::ima_unavailable()),
+            _ => event_logs.extend(self.event_logs.to_vec()),
         }
 
+        Ok(event_logs)
+    }
+
+    pub fn get_cc_eventlog(
+        &mut self,
+        scope: Scope,
+        start: Option<u32>,
+        count: Option<u32>,
+    ) -> Result<Vec<TcgEventlog>, Error> {
+        let _ = self.fetch_all_event_logs();
+        let event_logs = self.scoped_event_logs(&scope)?;
+
         let begin = match start {
             Some(s) => match s.cmp(&(event_logs.len() as u32)) {
                 Ordering::Greater => {
@@ -269,64 +546,152 @@ impl Agent {
         Ok(event_logs[begin as usize..end as usize].to_vec())
     }
 
-    pub fn get_cc_report(
+    // The input of the backend for the report of the scope.
+    fn report_input(
+        &self,
+        scope: &Scope,
+        nonce: &Option<String>,
+        user_data: &Option<String>,
+    ) -> Result<ReportInput, Error> {
+        match (scope, self.mode) {
+            (Scope::Container(container_id), IsolationMode::Isolated) => {
+                if !self.containers.contains_key(container_id) {
+                    return Err(anyhow!("Container cannot be found."));
+                }
+
+                let container = &self.containers[container_id];
+                let binding = new_binding(
+                    container_id,
+                    container.imr(),
+                    container.event_logs().len() as u32,
+                );
+                let digest = binding_digest(
+                    &binding,
+                    &report_data::decode("nonce", nonce, MAX_NONCE_SIZE)?,
+                    &report_data::decode("user_data", user_data, MAX_USER_DATA_SIZE)?,
+                )?;
+                let (nonce, user_data) =
+                    report_data::fit(&self.cc_type, Some(base64::encode(digest)), None)?;
+                Ok((nonce, user_data, Some(binding)))
+            }
+            (Scope::Container(_), IsolationMode::Refuse) => Err(Agent::ima_unavailable()),
+            _ => {
+                let (nonce, user_data) =
+                    report_data::fit(&self.cc_type, nonce.clone(), user_data.clone())?;
+                Ok((nonce, user_data, None))
+            }
+        }
+    }
+
+    fn backend_cc_report(
+        &self,
+        scope: &Scope,
+        input: ReportInput,
+    ) -> Result<GetCcReportResponse, Error> {
+        let (nonce, user_data, binding) = input;
+        let start = Instant::now();
+        let (cc_report, cc_type) = match API::get_cc_report(nonce, user_data, ExtraArgs {}) {
+            Ok(v) => (v.cc_report, v.cc_type as i32),
+            Err(e) => {
+                metrics::backend_error("get_cc_report");
+                return Err(e);
+            }
+        };
+        metrics::REPORT_DURATION.observe(start.elapsed().as_secs_f64());
+
+        Ok(GetCcReportResponse {
+            cc_type,
+            cc_report,
+            unfiltered: self.report_unbound(scope),
+            binding,
+        })
+    }
+
+    // The report of the scope, generating it takes a report token of the caller.
+    fn scoped_cc_report(
         &mut self,
-        container_id: String,
+        scope: &Scope,
+        caller: &str,
         nonce: Option<String>,
         user_data: Option<String>,
-    ) -> Result<(Vec<u8>, i32), Error> {
-        let _ = self.fetch_all_event_logs();
+    ) -> Result<GetCcReportResponse, Error> {
+        let input = self.report_input(scope, &nonce, &user_data)?;
+        self.take_report_token(caller)?;
+        self.backend_cc_report(scope, input)
+    }
 
-        let new_nonce = if self.ima_enabled {
-            let measurement = match self.measurement.as_mut() {
-                Some(v) => v,
-                None => return Err(anyhow!("The measurement was not initialized.")),
-            };
+    // The buckets are keyed by the identity the connection gives to the caller, so a caller
+    // cannot get more reports by claiming other container IDs.
+    fn take_report_token(&mut self, caller: &str) -> Result<(), Error> {
+        let limiter = match self.report_limiter.as_mut() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
 
-            if measurement.container_isolated() {
-                if !self.containers.contains_key(&container_id) {
-                    return Err(anyhow!("Container cannot be found."));
-                }
+        match limiter.take(caller) {
+            Ok(_) => Ok(()),
+            Err(retry_after) => Err(ratelimit::exhausted("CC report", retry_after).into()),
+        }
+    }
 
-                let container = &self.containers[&container_id];
-                match nonce {
-                    Some(v) => match base64::decode(v) {
-                        Ok(v) => Some(base64::encode([container.imr().hash.to_vec(), v].concat())),
-                        Err(e) => return Err(anyhow!("nonce is not base64 encoded: {:?}", e)),
-                    },
-                    None => None,
-                }
-            } else {
-                nonce.clone()
+    // The cache key of a report, None when the cache is disabled.
+    fn report_key(
+        &self,
+        scope: &Scope,
+        nonce: &Option<String>,
+        user_data: &Option<String>,
+    ) -> Option<ReportKey> {
+        self.report_cache.as_ref()?;
+        let state = match (scope, self.mode) {
+            (Scope::Container(container_id), IsolationMode::Isolated) => {
+                let container = self.containers.get(container_id)?;
+                let count = container.event_logs().len() as u64;
+                [container.imr().hash.clone(), count.to_le_bytes().to_vec()].concat()
             }
-        } else {
-            nonce.clone()
+            _ => (self.event_logs.len() as u64).to_le_bytes().to_vec(),
         };
 
-        let (report, cc_type) = match API::get_cc_report(new_nonce, user_data, ExtraArgs {}) {
-            Ok(v) => (v.cc_report, v.cc_type as i32),
-            Err(e) => return Err(e),
-        };
-
-        Ok((report, cc_type))
+        Some(ReportKey {
+            scope: scope.clone(),
+            state,
+            nonce: nonce.clone(),
+            user_data: user_data.clone(),
+        })
     }
 
-    pub fn get_cc_measurement(
+    pub fn get_cc_report(
         &mut self,
-        container_id: String,
-        index: u32,
-        algo_id: u32,
-    ) -> Result<TcgDigest, Error> {
+        scope: Scope,
+        caller: &str,
+        nonce: Option<String>,
+        user_data: Option<String>,
+    ) -> Result<GetCcReportResponse, Error> {
         let _ = self.fetch_all_event_logs();
 
-        if self.ima_enabled {
-            let measurement = match self.measurement.as_mut() {
-                Some(v) => v,
-                None => return Err(anyhow!("The measurement was not initialized.")),
-            };
+        // Cached reports are not counted against the rate limit.
+        let key = self.report_key(&scope, &nonce, &user_data);
+        if let (Some(cache), Some(key)) = (self.report_cache.as_mut(), &key) {
+            if let Some(report) = cache.get(key) {
+                return Ok(report);
+            }
+        }
+
+        let report = self.scoped_cc_report(&scope, caller, nonce, user_data)?;
+        if let (Some(cache), Some(key)) = (self.report_cache.as_mut(), key) {
+            cache.insert(key, report.clone());
+        }
+        Ok(report)
+    }
 
-            if measurement.container_isolated() {
-                if !self.containers.contains_key(&container_id) {
+    fn scoped_cc_measurement(
+        &self,
+        scope: &Scope,
+        index: u32,
+        algo_id: u32,
+    ) -> Result<TcgDigest, Error> {
+        match (scope, self.mode) {
+            (Scope::Container(container_id), IsolationMode::Isolated) => {
+                if !self.containers.contains_key(container_id) {
                     return Err(anyhow!("Container cannot be found."));
                 }
 
@@ -335,7 +700,7 @@ impl Agent {
                 }
 
                 if index == IMR::CONTAINER as u32 {
-                    let container = match self.containers.get_mut(&container_id) {
+                    let container = match self.containers.get(container_id) {
                         Some(v) => v,
                         None => {
                             return Err(anyhow!(
@@ -346,6 +711,13 @@ impl Agent {
                     return Ok(container.imr().clone());
                 }
             }
+            (Scope::Container(_), IsolationMode::FirmwareOnly)
+                if index != IMR::FIRMWARE as u32 && index != IMR::KERNEL as u32 =>
+            {
+                return Err(anyhow!("Cannot access IMR according to the policy."));
+            }
+            (Scope::Container(_), IsolationMode::Refuse) => return Err(Agent::ima_unavailable()),
+            _ => {}
         }
 
         let measurement =
@@ -354,9 +726,228 @@ impl Agent {
                     algo_id: v.algo_id.into(),
                     hash: v.hash,
                 },
-                Err(e) => return Err(e),
+                Err(e) => {
+                    metrics::backend_error("get_cc_measurement");
+                    return Err(e);
+                }
             };
 
         Ok(measurement)
     }
+
+    pub fn get_cc_measurement(
+        &mut self,
+        scope: Scope,
+        index: u32,
+        algo_id: u32,
+    ) -> Result<TcgDigest, Error> {
+        let _ = self.fetch_all_event_logs();
+        self.scoped_cc_measurement(&scope, index, algo_id)
+    }
+
+    fn scoped_measurements(&self, scope: &Scope) -> Result<Vec<ImrMeasurement>, Error> {
+        let algo_id: u32 = API::get_default_algorithm()?.algo_id.into();
+        let mut measurements = vec![];
+        for index in self.visible_imr_indexes(scope)? {
+            measurements.push(ImrMeasurement {
+                index,
+                measurement: Some(self.scoped_cc_measurement(scope, index, algo_id)?),
+            });
+        }
+
+        Ok(measurements)
+    }
+
+    pub fn get_evidence(
+        &mut self,
+        scope: Scope,
+        caller: &str,
+        nonce: Option<String>,
+        user_data: Option<String>,
+    ) -> Result<GetEvidenceResponse, Error> {
+        let _ = self.fetch_all_event_logs();
+        self.report_input(&scope, &nonce, &user_data)?;
+        self.take_report_token(caller)?;
+
+        /*
+         * The measurements and the number of events are read before and after the report is
+         * generated. When nothing is measured meanwhile, the report, the measurements and
+         * the event logs are all of the same state. The container IMR bound into the report
+         * is the one replayed from the returned logs.
+         */
+        let ((measurements, _), report) = consistent(
+            self,
+            EVIDENCE_ATTEMPTS,
+            |agent| {
+                let _ = agent.fetch_all_event_logs();
+                Ok((agent.scoped_measurements(&scope)?, agent.event_logs.len()))
+            },
+            |agent| {
+                let input = agent.report_input(&scope, &nonce, &user_data)?;
+                agent.backend_cc_report(&scope, input)
+            },
+        )?;
+
+        Ok(GetEvidenceResponse {
+            cc_type: report.cc_type,
+            cc_report: report.cc_report,
+            measurements,
+            event_logs: self.scoped_event_logs(&scope)?,
+            unfiltered: self.unfiltered(&scope),
+            binding: report.binding,
+        })
+    }
+
+    fn readiness_check(name: &str, result: Result<String, Error>) -> ReadinessCheck {
+        match result {
+            Ok(reason) => ReadinessCheck {
+                name: name.to_string(),
+                ok: true,
+                reason,
+            },
+            Err(e) => ReadinessCheck {
+                name: name.to_string(),
+                ok: false,
+                reason: e.to_string(),
+            },
+        }
+    }
+
+    // Read the measurements of the IMRs, which needs no lock on the agent.
+    pub fn measured_imrs() -> Result<Vec<TcgDigest>, Error> {
+        let algo_id = API::get_default_algorithm()?.algo_id;
+        let count: u32 = API::get_measurement_count()?.into();
+
+        let mut measurements = vec![];
+        for index in 0..count {
+            let measurement = API::get_cc_measurement(index.try_into()?, algo_id)?;
+            measurements.push(TcgDigest {
+                algo_id: measurement.algo_id.into(),
+                hash: measurement.hash,
+            });
+        }
+        Ok(measurements)
+    }
+
+    /*
+     * Replay the event logs of each IMR and compare them with the measurements. The
+     * measurements are read before the event logs are fetched, so an IMR is consistent
+     * when any prefix of its events replays to the measurement. Only the events after
+     * the prefix matched by the last check are replayed.
+     */
+    pub fn replay_event_logs(&mut self, measurements: &[TcgDigest]) -> Result<String, Error> {
+        if self.replays.len() < measurements.len() {
+            self.replays.resize(measurements.len(), Replay::default());
+        }
+
+        let mut replayed = 0;
+        for (index, measurement) in measurements.iter().enumerate() {
+            let replay = &mut self.replays[index];
+            if replay.algo_id != measurement.algo_id || replay.imr.len() != measurement.hash.len() {
+                *replay = Replay {
+                    algo_id: measurement.algo_id,
+                    imr: vec![0; measurement.hash.len()],
+                    ..Default::default()
+                };
+            }
+
+            let algo = MessageDigest::from(measurement.clone());
+            let mut imr = replay.imr.clone();
+            let mut events = replay.events;
+            let mut consistent = imr == measurement.hash;
+            for (position, event) in self.event_logs.iter().enumerate().skip(replay.position) {
+                if event.imr_index != index as u32 || event.event_type == tcg::EV_NO_ACTION {
+                    continue;
+                }
+                let digest = match event
+                    .digests
+                    .iter()
+                    .find(|d| d.algo_id == measurement.algo_id)
+                {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+                imr = hash(algo, &[imr, digest.hash.clone()].concat())?.to_vec();
+                events += 1;
+                if imr == measurement.hash {
+                    consistent = true;
+                    replay.imr = imr.clone();
+                    replay.position = position + 1;
+                    replay.events = events;
+                }
+            }
+            if events == 0 {
+                continue;
+            }
+            if !consistent {
+                return Err(anyhow!(
+                    "The event logs of IMR {} do not replay to its measurement.",
+                    index
+                ));
+            }
+            replayed += 1;
+        }
+
+        Ok(format!(
+            "The event logs of {} IMRs are consistent.",
+            replayed
+        ))
+    }
+
+    // Ingest the new event logs ahead of the requests.
+    pub fn ingest(&mut self) -> Result<(), Error> {
+        self.fetch_all_event_logs()
+    }
+
+    /*
+     * The readiness of the agent, it is ready when all the checks pass. The measurements
+     * are read by measured_imrs before the agent is locked.
+     */
+    pub fn get_readiness(
+        &mut self,
+        measurements: Result<Vec<TcgDigest>, Error>,
+    ) -> GetReadinessResponse {
+        let mut checks = vec![];
+
+        let policy = if self.initialized {
+            Ok(format!(
+                "The policy is loaded in {} mode.",
+                self.mode.as_str_name()
+            ))
+        } else {
+            Err(anyhow!("The policy is not loaded."))
+        };
+        checks.push(Agent::readiness_check("policy", policy));
+
+        let backend = match &measurements {
+            Ok(v) => Ok(format!("The backend reports {} IMRs.", v.len())),
+            Err(e) => Err(anyhow!("The backend is not reachable: {}", e)),
+        };
+        checks.push(Agent::readiness_check("backend", backend));
+
+        let ingestion = match self.fetch_all_event_logs() {
+            Ok(_) => Ok(format!("{} event logs are loaded.", self.event_log_count())),
+            Err(e) => Err(match self.last_ingestion {
+                Some(v) => anyhow!(
+                    "The event log ingestion fails since {}s: {}",
+                    v.elapsed().as_secs(),
+                    e
+                ),
+                None => anyhow!("The event logs have never been ingested: {}", e),
+            }),
+        };
+        checks.push(Agent::readiness_check("ingestion", ingestion));
+
+        let replay = match &measurements {
+            Ok(v) => self.replay_event_logs(v),
+            Err(_) => Err(anyhow!("The measurements are not available.")),
+        };
+        checks.push(Agent::readiness_check("replay", replay));
+
+        GetReadinessResponse {
+            ready: checks.iter().all(|c| c.ok),
+            checks,
+        }
+    }
 }
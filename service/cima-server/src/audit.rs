@@ -0,0 +1,179 @@
+use anyhow::Error;
+use log::error;
+use openssl::hash::{hash, MessageDigest};
+use serde::Serialize;
+use std::fs::{read_to_string, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::{transport::server::UdsConnectInfo, Code, Request, Status};
+
+use crate::{container::Container, identity::cert_subjects, vsock::VsockConnectInfo};
+
+// Who sent a request, as far as the transport tells.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Caller {
+    pub transport: &'static str,
+    pub pid: Option<i32>,
+    pub cgroup: Option<String>,
+    // The TLS certificate subjects or the vsock CID of a remote caller.
+    pub peer: Option<String>,
+    pub derived_container_id: Option<String>,
+}
+
+impl Caller {
+    pub fn of<T>(request: &Request<T>) -> Caller {
+        if let Some(info) = request.extensions().get::<VsockConnectInfo>() {
+            return Caller {
+                transport: "vsock",
+                peer: Some(format!("cid:{}", info.peer_cid)),
+                ..Default::default()
+            };
+        }
+
+        if let Some(certs) = request.peer_certs() {
+            let subjects = match certs.first().map(|c| cert_subjects(c.get_ref())) {
+                Some(Ok(v)) => Some(v.join(",")),
+                _ => None,
+            };
+            return Caller {
+                transport: "tls",
+                peer: subjects,
+                ..Default::default()
+            };
+        }
+
+        let pid = match request.extensions().get::<UdsConnectInfo>() {
+            Some(v) => v.peer_cred.and_then(|c| c.pid()),
+            None => None,
+        };
+        Caller {
+            transport: "uds",
+            pid,
+            cgroup: pid.and_then(cgroup_of_process),
+            derived_container_id: pid.and_then(|p| Container::id_of_process(p).ok()),
+            ..Default::default()
+        }
+    }
+
+    /*
+     * The identity of the caller given by the connection, whatever the request claims. A
+     * local caller whose cgroup cannot be read is told apart by its PID, and one without
+     * PID is refused, so that such callers do not share one identity.
+     */
+    pub fn identity(&self) -> Result<String, Error> {
+        if let Some(v) = self.peer.as_ref().or(self.cgroup.as_ref()) {
+            return Ok(format!("{}:{}", self.transport, v));
+        }
+
+        match self.pid {
+            Some(pid) => Ok(format!("{}:pid:{}", self.transport, pid)),
+            None => Err(Status::permission_denied("Cannot identify the caller process.").into()),
+        }
+    }
+}
+
+// The cgroup v2 path of the process, or the path of its first cgroup v1 hierarchy.
+fn cgroup_of_process(pid: i32) -> Option<String> {
+    let cgroup = read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let path = match cgroup.lines().find(|l| l.starts_with("0::")) {
+        Some(v) => v,
+        None => cgroup.lines().next()?,
+    };
+    path.splitn(3, ':').nth(2).map(|v| v.to_string())
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuditRecord {
+    pub timestamp: f64,
+    pub method: &'static str,
+    #[serde(flatten)]
+    pub caller: Caller,
+    pub claimed_container_id: String,
+    pub nonce_sha256: Option<String>,
+    // "ok", "denied" when the caller is not allowed, or "error".
+    pub result: &'static str,
+    pub code: String,
+    pub reason: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new<T>(
+        method: &'static str,
+        caller: Caller,
+        claimed_container_id: &str,
+        nonce: Option<&String>,
+        result: &Result<T, Error>,
+    ) -> AuditRecord {
+        let (code, reason) = match result {
+            Ok(_) => (Code::Ok, None),
+            Err(e) => match e.downcast_ref::<Status>() {
+                Some(s) => (s.code(), Some(s.message().to_string())),
+                None => (Code::Internal, Some(e.to_string())),
+            },
+        };
+        let result = match code {
+            Code::Ok => "ok",
+            Code::PermissionDenied | Code::Unauthenticated => "denied",
+            _ => "error",
+        };
+
+        AuditRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|v| v.as_secs_f64())
+                .unwrap_or_default(),
+            method,
+            caller,
+            claimed_container_id: claimed_container_id.to_string(),
+            nonce_sha256: nonce.map(|v| nonce_hash(v)),
+            result,
+            code: format!("{:?}", code),
+            reason,
+        }
+    }
+}
+
+// SHA-256 of the decoded nonce, or of the nonce as sent when it is not base64.
+fn nonce_hash(nonce: &str) -> String {
+    let data = base64::decode(nonce).unwrap_or_else(|_| nonce.as_bytes().to_vec());
+    match hash(MessageDigest::sha256(), &data) {
+        Ok(v) => v.iter().map(|b| format!("{:02x}", b)).collect(),
+        Err(_) => String::new(),
+    }
+}
+
+// Append-only JSON lines log of the evidence requests.
+pub struct AuditLog {
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    pub fn new(path: &str) -> Result<AuditLog, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)?;
+        Ok(AuditLog {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, record: &AuditRecord) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to encode audit record: {:?}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        let mut file = self.file.lock().expect("Audit log lock() failed.");
+        if let Err(e) = file.write_all(&line) {
+            error!("Failed to write audit record: {:?}", e);
+        }
+    }
+}
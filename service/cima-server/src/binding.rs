@@ -0,0 +1,50 @@
+use anyhow::Error;
+use openssl::hash::{Hasher, MessageDigest};
+
+use crate::cima_pb::{ReportBinding, TcgDigest};
+
+/*
+ * Binding scheme v1, the CC report of a container is requested with the nonce
+ *      SHA384("CIMA-REPORT-BINDING-V1" || container_id || container_imr.algo_id ||
+ *             container_imr.hash || event_count || nonce || user_data)
+ * and no user data, where container_id, container_imr.hash, nonce and user_data are
+ * prefixed with their length as u32 little endian, algo_id is u16 little endian and
+ * event_count is u32 little endian. nonce and user_data are the base64 decoded values
+ * from the request. For TDX, REPORTDATA is SHA512 of the binding.
+ */
+pub const BINDING_VERSION: u32 = 1;
+const BINDING_TAG: &[u8] = b"CIMA-REPORT-BINDING-V1";
+
+fn update_with_len(hasher: &mut Hasher, data: &[u8]) -> Result<(), Error> {
+    hasher.update(&(data.len() as u32).to_le_bytes())?;
+    hasher.update(data)?;
+    Ok(())
+}
+
+pub fn binding_digest(
+    binding: &ReportBinding,
+    nonce: &[u8],
+    user_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let imr = binding.container_imr.clone().unwrap_or_default();
+
+    let mut hasher = Hasher::new(MessageDigest::sha384())?;
+    hasher.update(BINDING_TAG)?;
+    update_with_len(&mut hasher, binding.container_id.as_bytes())?;
+    hasher.update(&(imr.algo_id as u16).to_le_bytes())?;
+    update_with_len(&mut hasher, &imr.hash)?;
+    hasher.update(&binding.event_count.to_le_bytes())?;
+    update_with_len(&mut hasher, nonce)?;
+    update_with_len(&mut hasher, user_data)?;
+
+    Ok(hasher.finish()?.to_vec())
+}
+
+pub fn new_binding(container_id: &str, imr: &TcgDigest, event_count: u32) -> ReportBinding {
+    ReportBinding {
+        version: BINDING_VERSION,
+        container_id: container_id.to_string(),
+        container_imr: Some(imr.clone()),
+        event_count,
+    }
+}
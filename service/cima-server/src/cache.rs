@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{agent::Scope, cima_pb::GetCcReportResponse, metrics};
+
+pub const MAX_CACHED_REPORTS: usize = 1024;
+
+/*
+ * A report is reused only for the same scope, nonce and user_data while the state it
+ * was generated from is unchanged. The state is the container IMR and its event count
+ * for the isolated containers, or the number of the event logs of the node otherwise.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReportKey {
+    pub scope: Scope,
+    pub state: Vec<u8>,
+    pub nonce: Option<String>,
+    pub user_data: Option<String>,
+}
+
+struct CachedReport {
+    created: Instant,
+    report: GetCcReportResponse,
+}
+
+pub struct ReportCache {
+    ttl: Duration,
+    reports: HashMap<ReportKey, CachedReport>,
+}
+
+impl ReportCache {
+    pub fn new(ttl: Duration) -> ReportCache {
+        ReportCache {
+            ttl,
+            reports: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &ReportKey) -> Option<GetCcReportResponse> {
+        let report = match self.reports.get(key) {
+            Some(v) if v.created.elapsed() < self.ttl => Some(v.report.clone()),
+            _ => None,
+        };
+
+        match report {
+            Some(_) => metrics::REPORT_CACHE_HITS.inc(),
+            None => metrics::REPORT_CACHE_MISSES.inc(),
+        }
+        report
+    }
+
+    // Drop the expired reports and those of the scope generated from an older state.
+    pub fn insert(&mut self, key: ReportKey, report: GetCcReportResponse) {
+        let ttl = self.ttl;
+        self.reports.retain(|k, v| {
+            v.created.elapsed() < ttl && (k.scope != key.scope || k.state == key.state)
+        });
+
+        if self.reports.len() < MAX_CACHED_REPORTS {
+            self.reports.insert(
+                key,
+                CachedReport {
+                    created: Instant::now(),
+                    report,
+                },
+            );
+        }
+        metrics::REPORT_CACHE_ENTRIES.set(self.reports.len() as i64);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::cima_pb::TcgEventlog;
+
+// The CEL encoders are shared with the SDK, so both produce the same bytes.
+pub use cima_common::cel::*;
+
+impl CelEvent for TcgEventlog {
+    fn imr_index(&self) -> u32 {
+        self.imr_index
+    }
+
+    fn event_type(&self) -> u32 {
+        self.event_type
+    }
+
+    fn event_data(&self) -> &[u8] {
+        &self.event
+    }
+
+    fn digests(&self) -> Vec<(u32, &[u8])> {
+        self.digests
+            .iter()
+            .map(|d| (d.algo_id, d.hash.as_slice()))
+            .collect()
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+pub const DEFAULT_SOCK: &str = "/run/cima/uds/cima-server.sock";
+pub const DEFAULT_SOCK_MODE: u32 = 0o666;
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketConfig {
+    pub path: Option<String>,
+    // Octal permission bits of the socket file, e.g. "0660".
+    pub mode: Option<String>,
+    // Group owning the socket file.
+    pub group: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TcpConfig {
+    pub address: Option<String>,
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    pub tls_client_ca: Option<String>,
+    pub tls_identities: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VsockConfig {
+    pub port: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogConfig {
+    pub level: Option<String>,
+    // "text" or "json".
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitsConfig {
+    pub request_timeout_seconds: Option<u64>,
+    pub concurrency_per_connection: Option<usize>,
+}
+
+/*
+ * Runtime options of the server. Each option can also be given by a command line flag
+ * or an environment variable, which override the file.
+ */
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerConfig {
+    pub policy: Option<String>,
+    pub socket: SocketConfig,
+    pub tcp: TcpConfig,
+    pub vsock: VsockConfig,
+    pub metrics: Option<String>,
+    pub audit_log: Option<String>,
+    pub log: LogConfig,
+    // Ingest the event logs periodically, besides on each request.
+    pub ingestion_interval_seconds: Option<u64>,
+    pub health_check_interval_seconds: Option<u64>,
+    // Directory of the lock file, the directory of the socket by default.
+    pub state_dir: Option<String>,
+    pub limits: LimitsConfig,
+}
+
+impl ServerConfig {
+    pub fn new(path: String) -> Result<ServerConfig, Error> {
+        let file = std::fs::File::open(&path)
+            .map_err(|e| anyhow!("Failed to open server config file {}: {}", path, e))?;
+        serde_yaml::from_reader(file)
+            .map_err(|e| anyhow!("Failed to parse server config file {}: {}", path, e))
+    }
+
+    // Reject the values the server cannot run with, a zero interval or limit among them.
+    pub fn validate(&self) -> Result<(), Error> {
+        let positive = [
+            ("ingestionIntervalSeconds", self.ingestion_interval_seconds),
+            (
+                "healthCheckIntervalSeconds",
+                self.health_check_interval_seconds,
+            ),
+            (
+                "limits.requestTimeoutSeconds",
+                self.limits.request_timeout_seconds,
+            ),
+            (
+                "limits.concurrencyPerConnection",
+                self.limits.concurrency_per_connection.map(|v| v as u64),
+            ),
+        ];
+        for (name, value) in positive {
+            if value == Some(0) {
+                return Err(anyhow!("{} must be larger than 0.", name));
+            }
+        }
+
+        self.sock_mode()?;
+        Ok(())
+    }
+
+    pub fn sock(&self) -> String {
+        self.socket
+            .path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SOCK.to_string())
+    }
+
+    pub fn sock_mode(&self) -> Result<u32, Error> {
+        match &self.socket.mode {
+            Some(v) => match u32::from_str_radix(v.trim_start_matches("0o"), 8) {
+                Ok(v) if v <= 0o777 => Ok(v),
+                _ => Err(anyhow!("Invalid socket mode {}.", v)),
+            },
+            None => Ok(DEFAULT_SOCK_MODE),
+        }
+    }
+
+    pub fn sock_gid(&self) -> Result<Option<u32>, Error> {
+        let group = match &self.socket.group {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        if let Ok(gid) = group.parse::<u32>() {
+            return Ok(Some(gid));
+        }
+
+        let name = CString::new(group.as_str())?;
+        let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+        if entry.is_null() {
+            return Err(anyhow!("Unknown socket group {}.", group));
+        }
+        Ok(Some(unsafe { (*entry).gr_gid }))
+    }
+
+    pub fn lock_file(&self) -> String {
+        match &self.state_dir {
+            Some(v) => format!("{}/cima-server.lock", v.trim_end_matches('/')),
+            None => format!("{}.lock", self.sock()),
+        }
+    }
+}
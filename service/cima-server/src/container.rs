@@ -1,8 +1,14 @@
+use crate::agent::IMR;
 use crate::cima_pb::{TcgDigest, TcgEventlog};
 use anyhow::{anyhow, Error};
 use evidence_api::tcg;
-use openssl::hash::{Hasher, MessageDigest};
+use openssl::hash::{hash, Hasher, MessageDigest};
 use regex::Regex;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+
+// Event type of the measurements extended by the workloads through ExtendContainerMeasurement.
+pub const APPLICATION_EVENT: u32 = 0x8001_0001;
 
 impl From<TcgDigest> for MessageDigest {
     fn from(digest: TcgDigest) -> Self {
@@ -26,11 +32,20 @@ impl Container {
         Container { imr, event_logs }
     }
 
+    // Whether a cgroup path is one of a container, which parse_id then gets the ID of.
+    pub fn is_container_path(path: &str) -> bool {
+        path.contains("kubepods.slice") || path.contains("system.slice/docker-")
+    }
+
     pub fn parse_id(cgpath: Vec<&str>) -> Result<String, Error> {
-        let path = match cgpath[1].find("kubepods.slice") {
-            Some(v) => cgpath[1].get(v..),
-            None => match cgpath[1].find("system.slice") {
-                Some(v) => cgpath[1].get(v..),
+        Container::parse_id_from_path(cgpath[1])
+    }
+
+    pub fn parse_id_from_path(cgpath: &str) -> Result<String, Error> {
+        let path = match cgpath.find("kubepods.slice") {
+            Some(v) => cgpath.get(v..),
+            None => match cgpath.find("system.slice") {
+                Some(v) => cgpath.get(v..),
                 None => {
                     return Err(anyhow!(
                         "The container id parse failed, system.slice/kubepods.slice not found."
@@ -61,6 +76,27 @@ impl Container {
         Ok(id.to_string())
     }
 
+    // Get the container ID of a process from its cgroup path, in the same format as parse_id.
+    pub fn id_of_process(pid: i32) -> Result<String, Error> {
+        let cgroup = read_to_string(format!("/proc/{}/cgroup", pid))?;
+        for line in cgroup.lines() {
+            /*
+             * line format:
+             *      cgroup v1: {hierarchy-id}:{controllers}:{path}
+             *      cgroup v2: 0::{path}
+             */
+            let path = match line.splitn(3, ':').nth(2) {
+                Some(v) => v,
+                None => continue,
+            };
+            if Container::is_container_path(path) {
+                return Container::parse_id_from_path(path);
+            }
+        }
+
+        Err(anyhow!("The process {} is not in a container.", pid))
+    }
+
     pub fn imr(&self) -> &TcgDigest {
         &self.imr
     }
@@ -99,4 +135,36 @@ impl Container {
 
         Ok(())
     }
+
+    pub fn extend_application_event(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        pid: Option<i32>,
+    ) -> Result<(), Error> {
+        let event = [name.as_bytes(), &[0], data].concat();
+        let digest = hash(self.imr.clone().into(), &event)?;
+
+        let mut extra_info = HashMap::new();
+        extra_info.insert("source".to_string(), "application".to_string());
+        extra_info.insert("name".to_string(), name.to_string());
+        if let Some(pid) = pid {
+            extra_info.insert("pid".to_string(), pid.to_string());
+        }
+
+        let event_log = TcgEventlog {
+            rec_num: 0,
+            imr_index: 0,
+            event_type: APPLICATION_EVENT,
+            digests: vec![TcgDigest {
+                algo_id: self.imr.algo_id,
+                hash: digest.to_vec(),
+            }],
+            event_size: event.len() as u32,
+            event,
+            extra_info,
+        };
+
+        self.extend_imr(IMR::CONTAINER as u32, event_log)
+    }
 }
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Error};
+use evidence_api::tcg;
+use std::collections::HashMap;
+
+use crate::cima_pb::{TcgDigest, TcgEventlog};
+
+// The key of extra_info telling the format of the event in the backend event log.
+pub const EVENT_FORMAT_KEY: &str = "format";
+// The event has the single SHA-1 digest of the TCG PC Client format.
+pub const PCCLIENT_FORMAT: &str = "pcclient";
+
+// Convert an event of the backend event log into the record served to the clients.
+pub fn to_tcg_eventlog(entry: tcg::EventLogEntry) -> Result<TcgEventlog, Error> {
+    match entry {
+        tcg::EventLogEntry::TcgImrEvent(event) => {
+            let mut digests: Vec<TcgDigest> = vec![];
+            for d in event.digests {
+                digests.push(TcgDigest {
+                    algo_id: d.algo_id as u32,
+                    hash: d.hash,
+                })
+            }
+            Ok(TcgEventlog {
+                rec_num: 0,
+                imr_index: event.imr_index,
+                event_type: event.event_type,
+                event_size: event.event_size,
+                event: event.event,
+                digests,
+                extra_info: HashMap::new(),
+            })
+        }
+        tcg::EventLogEntry::TcgPcClientImrEvent(event) => {
+            let algo_id =
+                tcg::TcgDigest::get_algorithm_id_from_digest_size(event.digest.len() as u8);
+            let mut extra_info = HashMap::new();
+            extra_info.insert(EVENT_FORMAT_KEY.to_string(), PCCLIENT_FORMAT.to_string());
+
+            Ok(TcgEventlog {
+                rec_num: 0,
+                imr_index: event.imr_index,
+                event_type: event.event_type,
+                event_size: event.event_size,
+                event: event.event,
+                digests: vec![TcgDigest {
+                    algo_id: algo_id.into(),
+                    hash: event.digest.to_vec(),
+                }],
+                extra_info,
+            })
+        }
+        tcg::EventLogEntry::TcgCanonicalEvent(_) => Err(anyhow!(
+            "Canonical events of the backend event log are not supported."
+        )),
+    }
+}
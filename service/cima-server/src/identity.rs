@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Error};
+use openssl::{nid::Nid, x509::X509};
+use serde::{Deserialize, Serialize};
+use tonic::Status;
+
+use crate::agent::Scope;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Identity {
+    subject: String,
+    admin: Option<bool>,
+    containers: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityMap {
+    identities: Vec<Identity>,
+}
+
+impl IdentityMap {
+    pub fn new(path: String) -> Result<IdentityMap, Error> {
+        let file = std::fs::File::open(&path)
+            .map_err(|e| anyhow!("Failed to open client identity file {}: {}", path, e))?;
+        serde_yaml::from_reader(file)
+            .map_err(|e| anyhow!("Failed to parse client identity file {}: {}", path, e))
+    }
+
+    /*
+     * Map the subjects of a client certificate to a scope. An admin identity can query
+     * any container, and the whole node when no container ID is given. The others can
+     * only query the containers listed for them. container_id is None for the requests
+     * which are not about a container.
+     */
+    pub fn authorize(
+        &self,
+        subjects: &[String],
+        container_id: Option<&str>,
+    ) -> Result<Scope, Error> {
+        let identity = match self
+            .identities
+            .iter()
+            .find(|i| subjects.contains(&i.subject))
+        {
+            Some(v) => v,
+            None => {
+                return Err(Status::permission_denied(
+                    "The client certificate is not mapped to any identity.",
+                )
+                .into())
+            }
+        };
+        let admin = identity.admin.unwrap_or(false);
+
+        match container_id {
+            None if admin => Ok(Scope::System),
+            None => Ok(Scope::Container(String::new())),
+            Some("") if admin => Ok(Scope::System),
+            Some(id) => {
+                let allowed = match &identity.containers {
+                    Some(v) => v.iter().any(|c| c == id),
+                    None => false,
+                };
+                if admin || allowed {
+                    Ok(Scope::Container(id.to_string()))
+                } else {
+                    Err(Status::permission_denied(format!(
+                        "The client {} is not allowed to query the container.",
+                        identity.subject
+                    ))
+                    .into())
+                }
+            }
+        }
+    }
+}
+
+// The common name and the DNS/URI subject alternative names of a DER certificate.
+pub fn cert_subjects(der: &[u8]) -> Result<Vec<String>, Error> {
+    let cert = X509::from_der(der)?;
+    let mut subjects = vec![];
+
+    for entry in cert.subject_name().entries_by_nid(Nid::COMMONNAME) {
+        subjects.push(String::from_utf8_lossy(entry.data().as_slice()).to_string());
+    }
+
+    if let Some(names) = cert.subject_alt_names() {
+        for name in names {
+            if let Some(v) = name.dnsname() {
+                subjects.push(v.to_string());
+            }
+            if let Some(v) = name.uri() {
+                subjects.push(v.to_string());
+            }
+        }
+    }
+
+    Ok(subjects)
+}
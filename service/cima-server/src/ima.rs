@@ -0,0 +1,43 @@
+use crate::cel::{ima_template_data, IMA_TEMPLATE_NAME};
+use crate::cima_pb::TcgEventlog;
+use evidence_api::tcg;
+use openssl::hash::{hash, MessageDigest};
+
+// The PCR IMA extends on TPM, reported in ascii_runtime_measurements for the IMA tooling.
+pub const IMA_PCR: u32 = 10;
+
+/*
+ * Format an IMA event as a line of ascii_runtime_measurements:
+ *      {pcr} {template-hash} {template-name} {dep} {cg-path} {algo}:{digest} {file}
+ * The event data of ima-cgpath events is the space separated template fields. The
+ * template hash is the SHA-1 the kernel shows in ascii_runtime_measurements, over the
+ * template data with each field prefixed by its length, as ima_calc_field_array_hash
+ * computes it, so the IMA verifiers can recompute it.
+ */
+pub fn ascii_line(event: &TcgEventlog) -> Option<String> {
+    if event.event_type != tcg::IMA_MEASUREMENT_EVENT {
+        return None;
+    }
+
+    let fields = std::str::from_utf8(&event.event).ok()?;
+    let template_data = ima_template_data(&event.event)?;
+    let template_hash: String = hash(MessageDigest::sha1(), &template_data)
+        .ok()?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    Some(format!(
+        "{} {} {} {}",
+        IMA_PCR, template_hash, IMA_TEMPLATE_NAME, fields
+    ))
+}
+
+// Export the IMA events in the ascii_runtime_measurements format, other events are skipped.
+pub fn ascii_runtime_measurements(event_logs: &[TcgEventlog]) -> String {
+    event_logs
+        .iter()
+        .filter_map(ascii_line)
+        .map(|line| line + "\n")
+        .collect()
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Error};
+use log::info;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::time::Duration;
+use tokio::net::UnixListener;
+use tokio::signal::unix::{signal, SignalKind};
+
+// The first file descriptor passed by systemd socket activation.
+pub const LISTEN_FDS_START: i32 = 3;
+// How long the in-flight RPCs are given to finish on shutdown.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/*
+ * Take an exclusive lock on the lock file, so a second instance refuses to start instead
+ * of stealing the socket. The lock is held as long as the returned file is open, and is
+ * released by the kernel when the process exits.
+ */
+pub fn lock_instance(path: &str) -> Result<File, Error> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+        return Err(anyhow!(
+            "Another cima-server holds the lock file {}: {}",
+            path,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    file.set_len(0)?;
+    writeln!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+// The Unix socket passed by systemd socket activation, when LISTEN_PID is this process.
+pub fn activated_listener() -> Result<Option<UnixListener>, Error> {
+    let pid = match std::env::var("LISTEN_PID") {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(None);
+    }
+
+    let fds = std::env::var("LISTEN_FDS")?.parse::<i32>()?;
+    if fds < 1 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        return Err(anyhow!("Only one socket is supported, got {}.", fds));
+    }
+
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    info!("[cima-server]: using the socket passed by systemd.");
+    Ok(Some(UnixListener::from_std(listener)?))
+}
+
+// Resolve on SIGTERM or SIGINT.
+pub async fn shutdown_signal() -> Result<(), Error> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => info!("[cima-server]: received SIGTERM."),
+        _ = interrupt.recv() => info!("[cima-server]: received SIGINT."),
+    }
+    Ok(())
+}
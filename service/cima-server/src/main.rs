@@ -1,8 +1,21 @@
 pub mod agent;
+pub mod audit;
+pub mod binding;
+pub mod cache;
+pub mod cel;
+pub mod config;
 pub mod container;
+pub mod eventlog;
+pub mod identity;
+pub mod ima;
+pub mod lifecycle;
 pub mod measurement;
+pub mod metrics;
 pub mod policy;
+pub mod ratelimit;
+pub mod report_data;
 pub mod service;
+pub mod vsock;
 pub mod cima_pb {
     tonic::include_proto!("cima_server_pb");
 
@@ -10,78 +23,355 @@ pub mod cima_pb {
         tonic::include_file_descriptor_set!("cima_server_descriptor");
 }
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
-use log::info;
-use std::{fs, os::unix::fs::PermissionsExt};
-use tokio::net::UnixListener;
+use log::{error, info, warn};
+use std::ffi::CString;
+use std::io::Write;
+use std::{fs, future::Future, os::unix::fs::PermissionsExt, sync::Arc, time::Duration};
+use tokio::{net::UnixListener, sync::watch};
 use tokio_stream::wrappers::UnixListenerStream;
-use tonic::transport::Server;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 
+use audit::AuditLog;
 use cima_pb::{cima_server::CimaServer, FILE_DESCRIPTOR_SET};
+use config::{LogConfig, ServerConfig, TcpConfig, DEFAULT_LOG_LEVEL};
+use identity::IdentityMap;
+use metrics::MetricsLayer;
 use policy::PolicyConfig;
 use service::Service;
+use vsock::VsockListener;
 
 #[derive(Parser)]
 struct Cli {
-    /// UDS sock file
-    #[arg(short, long)]
-    #[clap(default_value = "/run/cima/uds/cima-server.sock")]
-    sock: String,
+    /// Server config file, overridden by the flags below
+    #[arg(short, long, env = "CIMA_CONFIG")]
+    config: Option<String>,
+    /// UDS sock file [default: /run/cima/uds/cima-server.sock]
+    #[arg(short, long, env = "CIMA_SOCK")]
+    sock: Option<String>,
+    /// Permission bits of the UDS sock file in octal [default: 0666]
+    #[arg(long, env = "CIMA_SOCK_MODE")]
+    sock_mode: Option<String>,
+    /// Group owning the UDS sock file
+    #[arg(long, env = "CIMA_SOCK_GROUP")]
+    sock_group: Option<String>,
     /// Input policy file
-    #[arg(short, long)]
-    policy: String,
+    #[arg(short, long, env = "CIMA_POLICY")]
+    policy: Option<String>,
+    /// TCP address to serve with mutual TLS, e.g. 0.0.0.0:8443
+    #[arg(long, env = "CIMA_TCP")]
+    tcp: Option<String>,
+    /// Server certificate of the TCP listener in PEM
+    #[arg(long, env = "CIMA_TLS_CERT")]
+    tls_cert: Option<String>,
+    /// Server private key of the TCP listener in PEM
+    #[arg(long, env = "CIMA_TLS_KEY")]
+    tls_key: Option<String>,
+    /// CA certificate to verify the TCP clients in PEM
+    #[arg(long, env = "CIMA_TLS_CLIENT_CA")]
+    tls_client_ca: Option<String>,
+    /// Client identity file mapping the client certificates to containers or admin
+    #[arg(long, env = "CIMA_TLS_IDENTITIES")]
+    tls_identities: Option<String>,
+    /// vsock port to serve the system evidence on
+    #[arg(long, env = "CIMA_VSOCK_PORT")]
+    vsock_port: Option<u32>,
+    /// Address to serve the Prometheus metrics on, e.g. 127.0.0.1:9090
+    #[arg(long, env = "CIMA_METRICS")]
+    metrics: Option<String>,
+    /// JSON lines file to record the evidence requests in
+    #[arg(long, env = "CIMA_AUDIT_LOG")]
+    audit_log: Option<String>,
+    /// Log level, e.g. debug or cima_server=debug [default: info]
+    #[arg(long, env = "CIMA_LOG_LEVEL")]
+    log_level: Option<String>,
+    /// Log format, text or json [default: text]
+    #[arg(long, env = "CIMA_LOG_FORMAT")]
+    log_format: Option<String>,
+    /// Interval in seconds to ingest the event logs in the background
+    #[arg(long, env = "CIMA_INGESTION_INTERVAL")]
+    ingestion_interval: Option<u64>,
+    /// Interval in seconds to check the health of the backend [default: 10]
+    #[arg(long, env = "CIMA_HEALTH_CHECK_INTERVAL")]
+    health_check_interval: Option<u64>,
+    /// Directory of the lock file [default: the directory of the sock file]
+    #[arg(long, env = "CIMA_STATE_DIR")]
+    state_dir: Option<String>,
 }
 
-fn set_sock_perm(sock: &str) -> Result<()> {
+fn override_with<T: Clone>(field: &mut Option<T>, value: &Option<T>) {
+    if value.is_some() {
+        *field = value.clone();
+    }
+}
+
+impl Cli {
+    // The config file with the flags and the environment variables applied over it.
+    fn server_config(&self) -> Result<ServerConfig> {
+        let mut config = match &self.config {
+            Some(v) => ServerConfig::new(v.clone())?,
+            None => ServerConfig::default(),
+        };
+
+        override_with(&mut config.policy, &self.policy);
+        override_with(&mut config.socket.path, &self.sock);
+        override_with(&mut config.socket.mode, &self.sock_mode);
+        override_with(&mut config.socket.group, &self.sock_group);
+        override_with(&mut config.tcp.address, &self.tcp);
+        override_with(&mut config.tcp.tls_cert, &self.tls_cert);
+        override_with(&mut config.tcp.tls_key, &self.tls_key);
+        override_with(&mut config.tcp.tls_client_ca, &self.tls_client_ca);
+        override_with(&mut config.tcp.tls_identities, &self.tls_identities);
+        override_with(&mut config.vsock.port, &self.vsock_port);
+        override_with(&mut config.metrics, &self.metrics);
+        override_with(&mut config.audit_log, &self.audit_log);
+        override_with(&mut config.log.level, &self.log_level);
+        override_with(&mut config.log.format, &self.log_format);
+        override_with(
+            &mut config.ingestion_interval_seconds,
+            &self.ingestion_interval,
+        );
+        override_with(
+            &mut config.health_check_interval_seconds,
+            &self.health_check_interval,
+        );
+        override_with(&mut config.state_dir, &self.state_dir);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn init_log(config: &LogConfig) -> Result<()> {
+    let level = config.level.as_deref().unwrap_or(DEFAULT_LOG_LEVEL);
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::new().default_filter_or(level));
+    match config.format.as_deref() {
+        None | Some("text") => {}
+        Some("json") => {
+            builder.format(|buf, record| {
+                let line = serde_json::json!({
+                    "timestamp": buf.timestamp().to_string(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                });
+                writeln!(buf, "{}", line)
+            });
+        }
+        Some(v) => return Err(anyhow!("Unknown log format {}.", v)),
+    }
+    builder.init();
+    Ok(())
+}
+
+fn tls_config(tcp: &TcpConfig) -> Result<ServerTlsConfig> {
+    let (cert, key, ca) = match (&tcp.tls_cert, &tcp.tls_key, &tcp.tls_client_ca) {
+        (Some(cert), Some(key), Some(ca)) => (fs::read(cert)?, fs::read(key)?, fs::read(ca)?),
+        _ => return Err(anyhow!("TLS certificate, key and client CA are required.")),
+    };
+    if tcp.tls_identities.is_none() {
+        return Err(anyhow!("TLS client identities are required."));
+    }
+
+    Ok(ServerTlsConfig::new()
+        .identity(Identity::from_pem(cert, key))
+        .client_ca_root(Certificate::from_pem(ca)))
+}
+
+fn server_builder(config: &ServerConfig) -> Server {
+    let mut builder = Server::builder();
+    if let Some(v) = config.limits.request_timeout_seconds {
+        builder = builder.timeout(Duration::from_secs(v));
+    }
+    if let Some(v) = config.limits.concurrency_per_connection {
+        builder = builder.concurrency_limit_per_connection(v);
+    }
+    builder
+}
+
+async fn serve_optional<F>(server: Option<F>) -> Result<(), tonic::transport::Error>
+where
+    F: Future<Output = Result<(), tonic::transport::Error>>,
+{
+    match server {
+        Some(v) => v.await,
+        None => Ok(()),
+    }
+}
+
+async fn wait_shutdown(mut shutdown: watch::Receiver<()>) {
+    let _ = shutdown.changed().await;
+}
+
+fn set_sock_perm(sock: &str, mode: u32, gid: Option<u32>) -> Result<()> {
     let mut perms = fs::metadata(sock)?.permissions();
-    perms.set_mode(0o666);
+    perms.set_mode(mode);
     fs::set_permissions(sock, perms)?;
+    if let Some(gid) = gid {
+        // An owner of -1 is left unchanged.
+        let path = CString::new(sock)?;
+        if unsafe { libc::chown(path.as_ptr(), libc::uid_t::MAX, gid) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-
     let cli = Cli::parse();
-    let sock = cli.sock;
-    let policy = PolicyConfig::new(cli.policy);
+    let config = cli.server_config()?;
+    init_log(&config.log)?;
+
+    let sock = config.sock();
+    let policy = match &config.policy {
+        Some(v) => PolicyConfig::new(v.clone())?,
+        None => return Err("The policy file is required.".into()),
+    };
 
-    let _ = std::fs::remove_file(sock.clone());
-    let uds = match UnixListener::bind(sock.clone()) {
-        Ok(r) => r,
-        Err(e) => panic!("[cima-server]: bind UDS socket error: {:?}", e),
+    let _lock = lifecycle::lock_instance(&config.lock_file())?;
+
+    // The socket is only created, and removed on exit, when it is not passed by systemd.
+    let (uds, owned_sock) = match lifecycle::activated_listener()? {
+        Some(v) => (v, false),
+        None => {
+            let _ = std::fs::remove_file(sock.clone());
+            let uds = match UnixListener::bind(sock.clone()) {
+                Ok(r) => r,
+                Err(e) => panic!("[cima-server]: bind UDS socket error: {:?}", e),
+            };
+            info!("[cima-server]: set sock file permissions: {}", sock);
+            set_sock_perm(&sock, config.sock_mode()?, config.sock_gid()?)?;
+            (uds, true)
+        }
     };
     let uds_stream = UnixListenerStream::new(uds);
-    info!("[cima-server]: set sock file permissions: {}", sock);
-    set_sock_perm(&sock.clone())?;
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
 
-    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
-    health_reporter.set_serving::<CimaServer<Service>>().await;
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
 
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
         .build()
         .unwrap();
 
+    let vsock_cids = policy.vsock_allowed_cids().cloned().unwrap_or_default();
     info!("[cima-server]: staring the service...");
-    let service = Service::new(policy);
-    Server::builder()
-        .add_service(reflection_service)
-        .add_service(health_service)
-        .add_service(CimaServer::new(service))
-        .serve_with_incoming(uds_stream)
-        .await?;
-    Ok(())
+    let mut service = Service::new(policy);
+    if let Some(path) = config.tcp.tls_identities.clone() {
+        service = service.with_identities(IdentityMap::new(path)?);
+    }
+    if let Some(path) = config.audit_log.clone() {
+        service = service.with_audit_log(AuditLog::new(&path)?);
+    }
+    let service = Arc::new(service);
+    tokio::spawn(service::watch_health(
+        health_reporter,
+        config
+            .health_check_interval_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(service::HEALTH_CHECK_INTERVAL),
+    ));
+    if let Some(v) = config.ingestion_interval_seconds {
+        tokio::spawn(service::watch_ingestion(Duration::from_secs(v)));
+    }
+
+    if let Some(v) = config.metrics.clone() {
+        let metrics_server = metrics::serve(v.parse()?)?;
+        tokio::spawn(async move {
+            if let Err(e) = metrics_server.await {
+                error!("[cima-server]: metrics server error: {:?}", e);
+            }
+        });
+    }
+
+    let uds_server = server_builder(&config)
+        .layer(MetricsLayer)
+        .add_service(reflection_service.clone())
+        .add_service(health_service.clone())
+        .add_service(CimaServer::from_arc(service.clone()))
+        .serve_with_incoming_shutdown(uds_stream, wait_shutdown(shutdown_rx.clone()));
+
+    let tcp_server = match config.tcp.address.clone() {
+        Some(v) => {
+            info!("[cima-server]: serving mutual TLS on {}", v);
+            Some(
+                server_builder(&config)
+                    .tls_config(tls_config(&config.tcp)?)?
+                    .layer(MetricsLayer)
+                    .add_service(reflection_service.clone())
+                    .add_service(health_service.clone())
+                    .add_service(CimaServer::from_arc(service.clone()))
+                    .serve_with_shutdown(v.parse()?, wait_shutdown(shutdown_rx.clone())),
+            )
+        }
+        None => None,
+    };
+
+    let vsock_server = match config.vsock.port {
+        Some(v) => {
+            info!("[cima-server]: serving vsock on port {}", v);
+            if vsock_cids.is_empty() {
+                warn!("[cima-server]: no vsock CID is allowed by the policy, all are denied");
+            }
+            Some(
+                server_builder(&config)
+                    .layer(MetricsLayer)
+                    .add_service(reflection_service)
+                    .add_service(health_service)
+                    .add_service(CimaServer::from_arc(service))
+                    .serve_with_incoming_shutdown(
+                        VsockListener::bind(v)?.incoming(),
+                        wait_shutdown(shutdown_rx),
+                    ),
+            )
+        }
+        None => None,
+    };
+
+    let servers = async {
+        tokio::try_join!(
+            uds_server,
+            serve_optional(tcp_server),
+            serve_optional(vsock_server)
+        )
+    };
+    tokio::pin!(servers);
+
+    // On SIGTERM/SIGINT, stop accepting connections and give the in-flight RPCs some time.
+    let result = tokio::select! {
+        r = &mut servers => r.map(|_| ()),
+        r = lifecycle::shutdown_signal() => {
+            r?;
+            info!("[cima-server]: draining the in-flight requests...");
+            let _ = shutdown_tx.send(());
+            match tokio::time::timeout(lifecycle::SHUTDOWN_TIMEOUT, &mut servers).await {
+                Ok(r) => r.map(|_| ()),
+                Err(_) => {
+                    warn!("[cima-server]: the requests are not drained in time.");
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    if owned_sock {
+        let _ = std::fs::remove_file(&sock);
+    }
+    info!("[cima-server]: stopped.");
+    Ok(result?)
 }
 
 #[cfg(test)]
 mod cima_server_test {
     use super::*;
-    use crate::agent::IMR;
+    use crate::agent::{Agent, Scope, IMR};
+    use crate::audit::{AuditRecord, Caller};
     use cima_pb::{
-        cima_client::CimaClient, GetCcEventlogRequest, GetCcMeasurementRequest, GetCcReportRequest,
+        cima_client::CimaClient, EventlogFormat, ExtendContainerMeasurementRequest,
+        GetCcEventlogRequest, GetCcMeasurementRequest, GetCcReportRequest, GetEvidenceRequest,
+        GetIsolationModeRequest, GetReadinessRequest, GetServerInfoRequest, IsolationMode,
     };
     use evidence_api::{cc_type::TeeType, tcg};
     use policy::PolicyConfig;
@@ -90,14 +380,28 @@ mod cima_server_test {
     use service::Service;
     use std::fs::read_to_string;
     use tokio::net::{UnixListener, UnixStream};
-    use tokio_stream::wrappers::UnixListenerStream;
+    use tokio_stream::{wrappers::UnixListenerStream, StreamExt};
+    use tonic::codegen::http;
     use tonic::transport::{Channel, Endpoint, Server, Uri};
     use tower::service_fn;
 
     async fn creat_server() {
+        creat_server_with_policy("./configs/policy.yaml").await
+    }
+
+    // The shipped policy with the application measurement turned on.
+    fn application_policy() -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let policy = read_to_string("./configs/policy.yaml")
+            .unwrap()
+            .replace("enabled: false", "enabled: true");
+        std::fs::write(file.path(), policy).unwrap();
+        file
+    }
+
+    async fn creat_server_with_policy(policy_path: &str) {
         let sock = String::from("/tmp/cima-server.sock");
-        let policy_path = String::from("./configs/policy.yaml");
-        let policy = PolicyConfig::new(policy_path);
+        let policy = PolicyConfig::new(policy_path.to_string()).unwrap();
 
         let _ = std::fs::remove_file(sock.clone());
         let uds = match UnixListener::bind(sock.clone()) {
@@ -106,7 +410,10 @@ mod cima_server_test {
         };
 
         let uds_stream = UnixListenerStream::new(uds);
-        assert!(set_sock_perm(&sock.clone()).is_ok(), "set_perm failed");
+        assert!(
+            set_sock_perm(&sock.clone(), config::DEFAULT_SOCK_MODE, None).is_ok(),
+            "set_perm failed"
+        );
 
         let service = Service::new(policy);
         tokio::spawn(async {
@@ -267,7 +574,7 @@ mod cima_server_test {
             .unwrap()
             .into_inner();
         let cc_measurement = response.measurement.unwrap();
-        assert_eq!(cc_measurement.algo_id, tcg::TPM_ALG_SHA384.into());
+        assert_eq!(cc_measurement.algo_id, u32::from(tcg::TPM_ALG_SHA384));
         assert_ne!(cc_measurement.hash.len(), 0)
     }
 
@@ -338,14 +645,15 @@ mod cima_server_test {
             container_id: container_id,
             start: Some(0),
             count: Some(1),
+            format: EventlogFormat::Tcg.into(),
         });
 
         let response = client.get_cc_eventlog(request).await.unwrap().into_inner();
         assert_eq!(response.event_logs.len(), 1);
 
         let event_log = response.event_logs[0].clone();
-        assert_eq!(event_log.event_type, tcg::EV_NO_ACTION.into());
-        assert_eq!(event_log.digests[0].algo_id, tcg::TPM_ALG_SHA1.into());
+        assert_eq!(event_log.event_type, tcg::EV_NO_ACTION);
+        assert_eq!(event_log.digests[0].algo_id, u32::from(tcg::TPM_ALG_SHA1));
         assert_ne!(event_log.event.len(), 0);
     }
 
@@ -359,6 +667,7 @@ mod cima_server_test {
             container_id: "".to_string(),
             start: Some(0),
             count: Some(1),
+            format: EventlogFormat::Tcg.into(),
         });
 
         let result = client.get_cc_eventlog(request).await;
@@ -378,9 +687,962 @@ mod cima_server_test {
             container_id: container_id,
             start: Some(0),
             count: Some(3),
+            format: EventlogFormat::Tcg.into(),
         });
 
         let response = client.get_cc_eventlog(request).await.unwrap().into_inner();
         assert_eq!(response.event_logs.len(), 3);
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_isolation_mode() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let request = tonic::Request::new(GetIsolationModeRequest {});
+
+        let response = client
+            .get_isolation_mode(request)
+            .await
+            .unwrap()
+            .into_inner();
+        if response.ima_enabled {
+            assert_eq!(response.mode, IsolationMode::Isolated as i32);
+        } else {
+            assert_eq!(response.mode, IsolationMode::Refuse as i32);
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_server_info() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let request = tonic::Request::new(GetServerInfoRequest {});
+
+        let response = client.get_server_info(request).await.unwrap().into_inner();
+        assert_eq!(response.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(response.cc_type, TeeType::TDX as i32);
+        assert_ne!(response.algo_ids.len(), 0);
+        if response.container_isolated {
+            assert!(response.imr_indexes.contains(&(IMR::CONTAINER as u32)));
+            assert!(!response.imr_indexes.contains(&(IMR::SYSTEM as u32)));
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_readiness() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let request = tonic::Request::new(GetReadinessRequest {});
+
+        let response = client.get_readiness(request).await.unwrap().into_inner();
+        let names: Vec<&str> = response.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["policy", "backend", "ingestion", "replay"]);
+        for check in response.checks.iter() {
+            assert!(check.ok, "{} check fails: {}", check.name, check.reason);
+        }
+        assert!(response.ready);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_evidence_normal() {
+        creat_server().await;
+        let mut client = create_client().await;
+        let user_data = base64::encode(rand::thread_rng().gen::<[u8; 32]>());
+        let nonce = base64::encode(rand::thread_rng().gen::<[u8; 32]>());
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetEvidenceRequest {
+            container_id,
+            user_data: Some(user_data),
+            nonce: Some(nonce),
+        });
+
+        let response = client.get_evidence(request).await.unwrap().into_inner();
+        assert_eq!(response.cc_type, TeeType::TDX as i32);
+        assert_ne!(response.cc_report.len(), 0);
+        assert_ne!(response.event_logs.len(), 0);
+        let container_imr = response
+            .measurements
+            .iter()
+            .find(|m| m.index == IMR::CONTAINER as u32)
+            .unwrap();
+        assert_ne!(container_imr.measurement.clone().unwrap().hash.len(), 0);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_evidence_empty_container_id() {
+        creat_server().await;
+        let mut client = create_client().await;
+        let nonce = base64::encode(rand::thread_rng().gen::<[u8; 32]>());
+
+        let request = tonic::Request::new(GetEvidenceRequest {
+            container_id: "".to_string(),
+            user_data: None,
+            nonce: Some(nonce),
+        });
+
+        let result = client.get_evidence(request).await;
+        assert!(result.is_err(), "Excepted an error");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_report_binding() {
+        creat_server().await;
+        let mut client = create_client().await;
+        let nonce = base64::encode(rand::thread_rng().gen::<[u8; 32]>());
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcReportRequest {
+            container_id: container_id.clone(),
+            user_data: None,
+            nonce: Some(nonce),
+        });
+
+        let response = client.get_cc_report(request).await.unwrap().into_inner();
+        let binding = response.binding.unwrap();
+        assert_eq!(binding.version, binding::BINDING_VERSION);
+        assert_eq!(binding.container_id, container_id);
+        assert_ne!(binding.event_count, 0);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_report_invalid_nonce() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcReportRequest {
+            container_id,
+            user_data: None,
+            nonce: Some("not base64!".to_string()),
+        });
+
+        let status = client.get_cc_report(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_report_oversized_nonce() {
+        creat_server().await;
+        let mut client = create_client().await;
+        let nonce = base64::encode(vec![0u8; report_data::MAX_NONCE_SIZE + 1]);
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcReportRequest {
+            container_id,
+            user_data: None,
+            nonce: Some(nonce),
+        });
+
+        let status = client.get_cc_report(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_extend_container_measurement_normal() {
+        let policy = application_policy();
+        creat_server_with_policy(policy.path().to_str().unwrap()).await;
+        let mut client = create_client().await;
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcEventlogRequest {
+            container_id: container_id.clone(),
+            start: None,
+            count: None,
+            format: EventlogFormat::Tcg.into(),
+        });
+        let before = client
+            .get_cc_eventlog(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .event_logs
+            .len();
+
+        let request = tonic::Request::new(ExtendContainerMeasurementRequest {
+            container_id,
+            name: "cima-test".to_string(),
+            event: b"application event".to_vec(),
+        });
+
+        let response = client
+            .extend_container_measurement(request)
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.measurement.is_some());
+        assert!(response.event_count as usize > before);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_extend_container_measurement_other_container() {
+        let policy = application_policy();
+        creat_server_with_policy(policy.path().to_str().unwrap()).await;
+        let mut client = create_client().await;
+
+        let request = tonic::Request::new(ExtendContainerMeasurementRequest {
+            container_id: "not-the-caller".to_string(),
+            name: "cima-test".to_string(),
+            event: b"application event".to_vec(),
+        });
+
+        let status = client
+            .extend_container_measurement(request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_eventlog_cel_json() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcEventlogRequest {
+            container_id,
+            start: Some(1),
+            count: Some(3),
+            format: EventlogFormat::CelJson.into(),
+        });
+
+        let response = client.get_cc_eventlog(request).await.unwrap().into_inner();
+        assert_eq!(response.event_logs.len(), 0);
+
+        let records: Vec<serde_json::Value> =
+            serde_json::from_slice(&response.cel_event_logs).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["recnum"], 1);
+        assert_eq!(records[0]["content_type"], "pcclient_std");
+        assert_eq!(records[0]["digests"][0]["hashAlg"], "sha384");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_eventlog_cel_cbor() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcEventlogRequest {
+            container_id,
+            start: None,
+            count: None,
+            format: EventlogFormat::CelCbor.into(),
+        });
+
+        let response = client.get_cc_eventlog(request).await.unwrap().into_inner();
+        assert_eq!(response.event_logs.len(), 0);
+
+        let records: ciborium::value::Value =
+            ciborium::de::from_reader(response.cel_event_logs.as_slice()).unwrap();
+        let records = records.as_array().unwrap();
+        assert_ne!(records.len(), 0);
+        assert!(records.iter().any(|r| r
+            .as_map()
+            .unwrap()
+            .iter()
+            .any(|(k, _v)| *k == ciborium::value::Value::from(cel::CEL_IMA_TEMPLATE))));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn request_to_cc_eventlog_ima_ascii() {
+        creat_server().await;
+        let mut client = create_client().await;
+
+        let container_id = get_container_id();
+        assert_ne!(container_id.len(), 0);
+
+        let request = tonic::Request::new(GetCcEventlogRequest {
+            container_id: container_id.clone(),
+            start: None,
+            count: None,
+            format: EventlogFormat::ImaAscii.into(),
+        });
+
+        let response = client.get_cc_eventlog(request).await.unwrap().into_inner();
+        assert_eq!(response.event_logs.len(), 0);
+        assert_ne!(response.ima_ascii_event_logs.len(), 0);
+
+        for line in response.ima_ascii_event_logs.lines() {
+            let fields: Vec<&str> = line.splitn(7, ' ').collect();
+            assert_eq!(fields.len(), 7);
+            assert_eq!(fields[0], ima::IMA_PCR.to_string());
+            assert_eq!(fields[2], cel::IMA_TEMPLATE_NAME);
+            assert_eq!(
+                container::Container::parse_id_from_path(fields[4]).unwrap(),
+                container_id
+            );
+        }
+    }
+
+    #[test]
+    fn ima_ascii_template_hash() {
+        /*
+         * The template hash of the line is the SHA-1 of the ima-cgpath template data, each
+         * field prefixed by its length, as ima_calc_field_array_hash computes it.
+         */
+        let cgpath = "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod8c0cbb8f_3f4a_4b56_9b8f_1d1f0e0a3f42.slice/cri-containerd-5e5f0c1f2e3d4c5b6a79880716253443f2e1d0c9b8a7968574635241302f1e0d.scope";
+        let fields = format!(
+            "/usr/bin/containerd-shim-runc-v2 {} sha256:{} /usr/bin/sh",
+            cgpath, "a8076d3d28d21e02012b20eaf7dbf75409a6277134439025f282e368e3305abf"
+        );
+        let event = cima_pb::TcgEventlog {
+            imr_index: IMR::CONTAINER as u32,
+            event_type: tcg::IMA_MEASUREMENT_EVENT,
+            event_size: fields.len() as u32,
+            event: fields.clone().into_bytes(),
+            digests: vec![cima_pb::TcgDigest {
+                algo_id: tcg::TPM_ALG_SHA384.into(),
+                hash: vec![0; 48],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            ima::ascii_line(&event).unwrap(),
+            format!(
+                "10 fdc4a0c5ba1648cd8705f9397dcfcc4ed8406ac2 ima-cgpath {}",
+                fields
+            )
+        );
+
+        let event = cima_pb::TcgEventlog {
+            event: b"boot_aggregate".to_vec(),
+            ..event
+        };
+        assert_eq!(ima::ascii_line(&event), None);
+    }
+
+    #[test]
+    fn identity_to_scope() {
+        let identities = IdentityMap::new(String::from("./configs/identities.yaml")).unwrap();
+        let container_id = "29134314a2d2a5ea4c8bd0ddbbfa1d4e8e06b7324c5ee6a0a6cb88a5a1b4c52b";
+
+        let admin = vec![String::from("node-verifier")];
+        assert_eq!(
+            identities.authorize(&admin, Some("")).unwrap(),
+            Scope::System
+        );
+        assert_eq!(identities.authorize(&admin, None).unwrap(), Scope::System);
+        assert_eq!(
+            identities.authorize(&admin, Some(container_id)).unwrap(),
+            Scope::Container(container_id.to_string())
+        );
+
+        let tenant = vec![String::from("tenant-verifier.example.com")];
+        assert_eq!(
+            identities.authorize(&tenant, Some(container_id)).unwrap(),
+            Scope::Container(container_id.to_string())
+        );
+        assert!(
+            identities.authorize(&tenant, Some("")).is_err(),
+            "Excepted an error"
+        );
+        assert!(
+            identities.authorize(&tenant, Some("other")).is_err(),
+            "Excepted an error"
+        );
+
+        let unknown = vec![String::from("unknown")];
+        assert!(
+            identities.authorize(&unknown, None).is_err(),
+            "Excepted an error"
+        );
+    }
+
+    #[test]
+    fn instance_lock() {
+        let path = std::env::temp_dir().join("cima-server-test.sock.lock");
+        let path = path.to_str().unwrap();
+
+        let lock = lifecycle::lock_instance(path).unwrap();
+        assert_eq!(
+            read_to_string(path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        assert!(lifecycle::lock_instance(path).is_err(), "Excepted an error");
+
+        drop(lock);
+        assert!(lifecycle::lock_instance(path).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn server_config_override() {
+        let cli = Cli::parse_from([
+            "cima_server",
+            "-c",
+            "./configs/server.yaml",
+            "--sock-mode",
+            "0600",
+            "--ingestion-interval",
+            "1",
+            "--log-level",
+            "debug",
+            "--health-check-interval",
+            "5",
+        ]);
+        let config = cli.server_config().unwrap();
+        assert_eq!(config.policy.as_deref(), Some("/etc/policy.yaml"));
+        assert_eq!(config.sock_mode().unwrap(), 0o600);
+        assert_eq!(config.ingestion_interval_seconds, Some(1));
+        assert_eq!(config.log.level.as_deref(), Some("debug"));
+        assert_eq!(config.health_check_interval_seconds, Some(5));
+        assert_eq!(config.log.format.as_deref(), Some("json"));
+        assert_eq!(config.lock_file(), "/run/cima/cima-server.lock");
+
+        let config = Cli::parse_from(["cima_server", "-p", "./configs/policy.yaml"])
+            .server_config()
+            .unwrap();
+        assert_eq!(config.sock(), config::DEFAULT_SOCK);
+        assert_eq!(config.sock_mode().unwrap(), config::DEFAULT_SOCK_MODE);
+        assert_eq!(config.sock_gid().unwrap(), None);
+        assert_eq!(config.lock_file(), format!("{}.lock", config::DEFAULT_SOCK));
+
+        let mut config = ServerConfig::default();
+        config.socket.mode = Some("0999".to_string());
+        assert!(config.sock_mode().is_err(), "Excepted an error");
+        assert!(config.validate().is_err(), "Excepted an error");
+    }
+
+    #[test]
+    fn server_config_validation() {
+        let cli = Cli::parse_from(["cima_server", "--ingestion-interval", "0"]);
+        assert!(cli.server_config().is_err(), "Excepted an error");
+
+        let cli = Cli::parse_from(["cima_server", "-c", "./configs/missing.yaml"]);
+        assert!(cli.server_config().is_err(), "Excepted an error");
+        let cli = Cli::parse_from(["cima_server", "--health-check-interval", "0"]);
+        assert!(cli.server_config().is_err(), "Excepted an error");
+
+        assert!(
+            PolicyConfig::new(String::from("./configs/missing.yaml")).is_err(),
+            "Excepted an error"
+        );
+        assert!(
+            IdentityMap::new(String::from("./configs/server.yaml")).is_err(),
+            "Excepted an error"
+        );
+
+        let mut config = ServerConfig::default();
+        assert!(config.validate().is_ok());
+        config.health_check_interval_seconds = Some(0);
+        assert!(config.validate().is_err(), "Excepted an error");
+
+        let mut config = ServerConfig::default();
+        config.limits.request_timeout_seconds = Some(0);
+        assert!(config.validate().is_err(), "Excepted an error");
+
+        let mut config = ServerConfig::default();
+        config.limits.concurrency_per_connection = Some(0);
+        assert!(config.validate().is_err(), "Excepted an error");
+    }
+
+    #[test]
+    fn event_log_backend_offset() {
+        let mut agent = Agent::new();
+        let event = tcg::EventLogEntry::TcgImrEvent(tcg::TcgImrEvent {
+            imr_index: IMR::FIRMWARE as u32,
+            event_type: tcg::EV_NO_ACTION,
+            digests: vec![tcg::TcgDigest {
+                algo_id: tcg::TPM_ALG_SHA384,
+                hash: vec![0; 48],
+            }],
+            event_size: 0,
+            event: vec![],
+        });
+
+        // The canonical events cannot be converted and are skipped.
+        let entries = vec![
+            event.clone(),
+            tcg::EventLogEntry::TcgCanonicalEvent(tcg::TcgCanonicalEvent {}),
+        ];
+        agent.ingest_event_logs(entries).unwrap();
+        assert_eq!(agent.event_log_count(), 1);
+        assert_eq!(agent.backend_offset(), 2);
+
+        // The next fetch starts after the skipped event, no event is loaded twice.
+        agent.ingest_event_logs(vec![event]).unwrap();
+        assert_eq!(agent.event_log_count(), 2);
+        assert_eq!(agent.backend_offset(), 3);
+    }
+
+    #[test]
+    fn server_info_algo_ids() {
+        let mut agent = Agent::new();
+        let event = |event_type: u32, algo_ids: &[u16]| {
+            tcg::EventLogEntry::TcgImrEvent(tcg::TcgImrEvent {
+                imr_index: IMR::FIRMWARE as u32,
+                event_type,
+                digests: algo_ids
+                    .iter()
+                    .map(|&algo_id| tcg::TcgDigest {
+                        algo_id,
+                        hash: vec![0; 48],
+                    })
+                    .collect(),
+                event_size: 0,
+                event: vec![],
+            })
+        };
+        let sha1: u32 = tcg::TPM_ALG_SHA1.into();
+        let sha256: u32 = tcg::TPM_ALG_SHA256.into();
+        let sha384: u32 = tcg::TPM_ALG_SHA384.into();
+
+        assert_eq!(agent.algo_ids(sha384), vec![sha384]);
+
+        // The digests of the Spec ID event are not extended, its algorithm is not listed.
+        let entries = vec![
+            event(tcg::EV_NO_ACTION, &[tcg::TPM_ALG_SHA1]),
+            event(
+                tcg::EV_EFI_ACTION,
+                &[tcg::TPM_ALG_SHA384, tcg::TPM_ALG_SHA256],
+            ),
+        ];
+        agent.ingest_event_logs(entries).unwrap();
+        assert_eq!(agent.algo_ids(sha384), vec![sha384, sha256]);
+        assert_eq!(agent.algo_ids(sha1), vec![sha1, sha256, sha384]);
+    }
+
+    #[test]
+    fn readiness_replay_incremental() {
+        let mut agent = Agent::new();
+        let event = |value: u8| {
+            tcg::EventLogEntry::TcgImrEvent(tcg::TcgImrEvent {
+                imr_index: IMR::KERNEL as u32,
+                event_type: tcg::EV_EFI_ACTION,
+                digests: vec![tcg::TcgDigest {
+                    algo_id: tcg::TPM_ALG_SHA384,
+                    hash: vec![value; 48],
+                }],
+                event_size: 0,
+                event: vec![],
+            })
+        };
+        let extend = |imr: &[u8], value: u8| {
+            let digest = openssl::hash::hash(
+                openssl::hash::MessageDigest::sha384(),
+                &[imr, &[value; 48]].concat(),
+            );
+            digest.expect("Excepted a digest").to_vec()
+        };
+        let measurements = |imr: &[u8]| {
+            vec![
+                cima_pb::TcgDigest {
+                    algo_id: tcg::TPM_ALG_SHA384.into(),
+                    hash: vec![0; 48],
+                },
+                cima_pb::TcgDigest {
+                    algo_id: tcg::TPM_ALG_SHA384.into(),
+                    hash: imr.to_vec(),
+                },
+            ]
+        };
+
+        let first = extend(&[0; 48], 1);
+        let second = extend(&first, 2);
+        agent.ingest_event_logs(vec![event(1), event(2)]).unwrap();
+
+        // The measurement read before the second event matches a prefix of the log.
+        assert!(agent.replay_event_logs(&measurements(&first)).is_ok());
+        assert!(agent.replay_event_logs(&measurements(&second)).is_ok());
+
+        // The replay goes on from the matched prefix, the measurement cannot go back.
+        agent.ingest_event_logs(vec![event(3)]).unwrap();
+        let third = extend(&second, 3);
+        assert!(agent.replay_event_logs(&measurements(&third)).is_ok());
+        assert!(agent.replay_event_logs(&measurements(&first)).is_err());
+        assert!(agent.replay_event_logs(&measurements(&[1; 48])).is_err());
+        assert!(agent.replay_event_logs(&measurements(&third)).is_ok());
+    }
+
+    // A state measured `changes` more times, reports are taken of the state at the time.
+    struct Measured {
+        changes: u32,
+        state: u32,
+        reports: Vec<u32>,
+    }
+
+    fn measured_consistent(changes: u32) -> Result<(u32, u32), anyhow::Error> {
+        let mut measured = Measured {
+            changes,
+            state: 0,
+            reports: vec![],
+        };
+        let result = agent::consistent(
+            &mut measured,
+            agent::EVIDENCE_ATTEMPTS,
+            |m| {
+                if m.changes > 0 {
+                    m.changes -= 1;
+                    m.state += 1;
+                }
+                Ok(m.state)
+            },
+            |m| {
+                m.reports.push(m.state);
+                Ok(m.state)
+            },
+        );
+        assert!(measured.reports.len() <= agent::EVIDENCE_ATTEMPTS);
+        result
+    }
+
+    #[test]
+    fn evidence_consistent_state() {
+        assert_eq!(measured_consistent(0).unwrap(), (0, 0));
+        // The report is taken again until the state is the same before and after it.
+        assert_eq!(measured_consistent(2).unwrap(), (2, 2));
+        let error = measured_consistent(4).expect_err("Excepted an error");
+        let status = error.downcast::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+    }
+
+    #[test]
+    fn report_data_fit() {
+        let nonce = Some(base64::encode([1u8; 32]));
+        let user_data = Some(base64::encode([2u8; 32]));
+        let (fitted_nonce, fitted_user_data) =
+            report_data::fit(&TeeType::SEV, nonce.clone(), user_data.clone()).unwrap();
+        assert_eq!((fitted_nonce, fitted_user_data), (nonce, user_data.clone()));
+
+        // Too large for the report data, both are replaced with their SHA512 digest.
+        let nonce = Some(base64::encode([1u8; 33]));
+        let (fitted_nonce, fitted_user_data) =
+            report_data::fit(&TeeType::SEV, nonce.clone(), user_data.clone()).unwrap();
+        let digest = openssl::hash::hash(
+            openssl::hash::MessageDigest::sha512(),
+            &[[1u8; 33].as_slice(), [2u8; 32].as_slice()].concat(),
+        )
+        .unwrap();
+        assert_eq!(fitted_nonce, Some(base64::encode(digest)));
+        assert_eq!(fitted_user_data, None);
+
+        // The TDX backend hashes them itself.
+        let (fitted_nonce, _) =
+            report_data::fit(&TeeType::TDX, nonce.clone(), user_data.clone()).unwrap();
+        assert_eq!(fitted_nonce, nonce);
+
+        for (nonce, user_data) in [
+            (Some(String::from("not base64!")), None),
+            (None, Some(String::from("not base64!"))),
+            (
+                Some(base64::encode(vec![0u8; report_data::MAX_NONCE_SIZE + 1])),
+                None,
+            ),
+        ] {
+            let error =
+                report_data::fit(&TeeType::SEV, nonce, user_data).expect_err("Excepted an error");
+            let status = error.downcast::<tonic::Status>().unwrap();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn report_data_sizes() {
+        assert_eq!(
+            report_data::report_data_size(&TeeType::TDX).unwrap(),
+            report_data::ReportDataSize::Hashed
+        );
+        for (tee, size) in [(TeeType::SEV, 64), (TeeType::CCA, 64), (TeeType::TPM, 66)] {
+            assert_eq!(
+                report_data::report_data_size(&tee).unwrap(),
+                report_data::ReportDataSize::Bytes(size)
+            );
+
+            // The largest input of the TEE is passed through, one byte more is hashed.
+            let nonce = Some(base64::encode(vec![1u8; size - 1]));
+            let user_data = Some(base64::encode([2u8]));
+            let fitted = report_data::fit(&tee, nonce.clone(), user_data.clone()).unwrap();
+            assert_eq!(fitted, (nonce, user_data.clone()));
+
+            let nonce = Some(base64::encode(vec![1u8; size]));
+            let (fitted_nonce, fitted_user_data) =
+                report_data::fit(&tee, nonce.clone(), user_data).unwrap();
+            assert_ne!(fitted_nonce, nonce);
+            assert_eq!(fitted_user_data, None);
+        }
+
+        let error = report_data::report_data_size(&TeeType::PLAIN).expect_err("Excepted an error");
+        let status = error.downcast::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        report_data::fit(&TeeType::PLAIN, None, None).expect_err("Excepted an error");
+    }
+
+    #[test]
+    fn vsock_allowed_cids() {
+        let info = vsock::VsockConnectInfo {
+            peer_cid: 2,
+            peer_port: 1024,
+        };
+        // No CID is allowed when the policy does not list them.
+        let error = vsock::check_peer(&[], &info).expect_err("Excepted an error");
+        let status = error.downcast::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(vsock::check_peer(&[2], &info).is_ok());
+        assert!(vsock::check_peer(&[3], &info).is_err());
+    }
+
+    // The accept results played back in order.
+    struct ScriptedListener(std::sync::Mutex<std::collections::VecDeque<std::io::Result<u32>>>);
+
+    impl vsock::Accept for ScriptedListener {
+        type Conn = u32;
+
+        fn poll_accept(
+            &self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<u32>> {
+            match self.0.lock().unwrap().pop_front() {
+                Some(v) => std::task::Poll::Ready(v),
+                None => std::task::Poll::Pending,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn vsock_accept_errors() {
+        let results = vec![
+            Err(std::io::ErrorKind::ConnectionAborted.into()),
+            Ok(1),
+            Err(std::io::Error::from_raw_os_error(libc::EMFILE)),
+            Ok(2),
+        ];
+        let listener = ScriptedListener(std::sync::Mutex::new(results.into()));
+        let mut incoming = vsock::VsockIncoming::new(listener);
+
+        // The aborted connection is skipped, and the server keeps accepting.
+        assert_eq!(incoming.next().await.unwrap().unwrap(), 1);
+        // Out of file descriptors, accepting is paused for a while.
+        let start = std::time::Instant::now();
+        assert_eq!(incoming.next().await.unwrap().unwrap(), 2);
+        assert!(start.elapsed() >= vsock::ACCEPT_BACKOFF);
+    }
+
+    #[test]
+    fn report_rate_limit() {
+        let mut limiter = ratelimit::RateLimiter::new(2, 60);
+        assert!(limiter.take("container").is_ok());
+        assert!(limiter.take("container").is_ok());
+        let retry_after = match limiter.take("container") {
+            Ok(_) => panic!("Excepted an error"),
+            Err(v) => v,
+        };
+        assert!(retry_after.as_millis() > 0 && retry_after.as_millis() <= 1000);
+        // The buckets are per caller.
+        assert!(limiter.take("other").is_ok());
+        assert_eq!(limiter.len(), 2);
+
+        // The callers are told apart by the connection, not by the container ID they claim.
+        let caller = Caller {
+            transport: "uds",
+            pid: Some(1),
+            cgroup: Some(String::from("/kubepods.slice/cri-containerd-a.scope")),
+            derived_container_id: Some(String::from("a")),
+            ..Default::default()
+        };
+        assert_eq!(
+            caller.identity().unwrap(),
+            "uds:/kubepods.slice/cri-containerd-a.scope"
+        );
+        let caller = Caller {
+            transport: "vsock",
+            peer: Some(String::from("cid:3")),
+            ..Default::default()
+        };
+        assert_eq!(caller.identity().unwrap(), "vsock:cid:3");
+
+        // Local callers without a cgroup do not share one bucket.
+        let caller = Caller {
+            transport: "uds",
+            pid: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(caller.identity().unwrap(), "uds:pid:2");
+        let caller = Caller {
+            transport: "uds",
+            ..Default::default()
+        };
+        let error = caller.identity().expect_err("Excepted an error");
+        let status = error.downcast::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        // The buckets refilled since their last use are dropped.
+        let mut limiter = ratelimit::RateLimiter::new(1, 60_000);
+        assert!(limiter.take("container").is_ok());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        limiter.sweep();
+        assert!(limiter.is_empty());
+        let mut limiter = ratelimit::RateLimiter::new(1, 1);
+        assert!(limiter.take("container").is_ok());
+        limiter.sweep();
+        assert_eq!(limiter.len(), 1);
+
+        let status = ratelimit::exhausted("CC report", retry_after);
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(status.metadata().get(ratelimit::RETRY_AFTER_KEY).is_some());
+
+        let status = ratelimit::busy("report");
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(
+            status.metadata().get(ratelimit::RETRY_AFTER_KEY).unwrap(),
+            "1000"
+        );
+    }
+
+    #[test]
+    fn report_cache_reuse() {
+        let mut cache = cache::ReportCache::new(std::time::Duration::from_millis(200));
+        let key = cache::ReportKey {
+            scope: Scope::Container(String::from("container")),
+            state: vec![1],
+            nonce: None,
+            user_data: None,
+        };
+        let report = cima_pb::GetCcReportResponse {
+            cc_report: vec![1, 2, 3],
+            ..Default::default()
+        };
+
+        assert!(cache.get(&key).is_none());
+        cache.insert(key.clone(), report.clone());
+        assert_eq!(cache.get(&key).unwrap(), report);
+
+        // The report is not reused once the container IMR changes.
+        let changed = cache::ReportKey {
+            state: vec![2],
+            ..key.clone()
+        };
+        assert!(cache.get(&changed).is_none());
+        cache.insert(changed.clone(), report.clone());
+        assert!(cache.get(&key).is_none());
+
+        let nonce = cache::ReportKey {
+            nonce: Some(String::from("bm9uY2U=")),
+            ..changed.clone()
+        };
+        assert!(cache.get(&nonce).is_none());
+
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert!(cache.get(&changed).is_none());
+    }
+
+    #[test]
+    fn audit_log_record() {
+        let path = std::env::temp_dir().join("cima-audit-test.log");
+        let _ = std::fs::remove_file(&path);
+        let audit = AuditLog::new(path.to_str().unwrap()).unwrap();
+
+        let caller = Caller {
+            transport: "uds",
+            pid: Some(1),
+            derived_container_id: Some(String::from("other")),
+            ..Default::default()
+        };
+        let result: Result<(), anyhow::Error> =
+            Err(tonic::Status::permission_denied("denied").into());
+        // base64 of "nonce"
+        let nonce = String::from("bm9uY2U=");
+        audit.record(&AuditRecord::new(
+            "GetCcReport",
+            caller,
+            "claimed",
+            Some(&nonce),
+            &result,
+        ));
+        audit.record(&AuditRecord::new(
+            "GetCcEventlog",
+            Caller::default(),
+            "",
+            None,
+            &Ok::<(), anyhow::Error>(()),
+        ));
+
+        let data = read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = data
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["method"], "GetCcReport");
+        assert_eq!(lines[0]["pid"], 1);
+        assert_eq!(lines[0]["claimed_container_id"], "claimed");
+        assert_eq!(lines[0]["derived_container_id"], "other");
+        assert_eq!(
+            lines[0]["nonce_sha256"],
+            "78377b525757b494427f89014f97d79928f3938d14eb51e20fb5dec9834eb304"
+        );
+        assert_eq!(lines[0]["result"], "denied");
+        assert_eq!(lines[0]["code"], "PermissionDenied");
+        assert_eq!(lines[0]["reason"], "denied");
+        assert_eq!(lines[1]["result"], "ok");
+        assert!(lines[1]["reason"].is_null());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn metrics_rpc_count() {
+        // A backend answering every call with a trailers-only PERMISSION_DENIED.
+        let backend = service_fn(|_: http::Request<tonic::body::BoxBody>| async {
+            let response = http::Response::builder()
+                .header("content-type", "application/grpc")
+                .header("grpc-status", "7")
+                .body(hyper::Body::empty())
+                .unwrap();
+            Ok::<_, std::convert::Infallible>(response)
+        });
+        let mut client = CimaClient::new(tower::Layer::layer(&MetricsLayer, backend));
+
+        let labels = ["GetIsolationMode", "PermissionDenied"];
+        let count = metrics::RPC_REQUESTS.with_label_values(&labels).get();
+        let samples = metrics::RPC_DURATION
+            .with_label_values(&["GetIsolationMode"])
+            .get_sample_count();
+
+        let result = client.get_isolation_mode(GetIsolationModeRequest {}).await;
+        assert_eq!(
+            result.expect_err("Excepted an error").code(),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            metrics::RPC_REQUESTS.with_label_values(&labels).get(),
+            count + 1
+        );
+        assert_eq!(
+            metrics::RPC_DURATION
+                .with_label_values(&["GetIsolationMode"])
+                .get_sample_count(),
+            samples + 1
+        );
+    }
 }
@@ -142,8 +142,4 @@ impl Measurement {
     pub fn measure(&mut self) -> Result<(), Error> {
         self.measure_system()
     }
-
-    pub fn container_isolated(&mut self) -> bool {
-        self.policy.container_isolated().unwrap_or(false)
-    }
 }
@@ -0,0 +1,224 @@
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, StatusCode,
+};
+use lazy_static::lazy_static;
+use log::info;
+use prometheus::{
+    register_gauge, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, Encoder, Gauge, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::convert::Infallible;
+use std::fs::read_to_string;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tonic::codegen::http;
+use tonic::server::NamedService;
+use tonic::Code;
+use tower::{Layer, Service as TowerService};
+
+use crate::{cima_pb::cima_server::CimaServer, service::Service};
+
+pub const IMA_COUNT_PATH: &str = "/sys/kernel/security/ima/runtime_measurements_count";
+
+lazy_static! {
+    pub static ref RPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "cima_rpc_requests_total",
+        "Number of the RPCs served, by method and gRPC status code.",
+        &["method", "code"]
+    )
+    .expect("Failed to register metric.");
+    pub static ref RPC_DURATION: HistogramVec = register_histogram_vec!(
+        "cima_rpc_duration_seconds",
+        "Latency of the RPCs, by method.",
+        &["method"]
+    )
+    .expect("Failed to register metric.");
+    pub static ref CONTAINERS: IntGauge = register_int_gauge!(
+        "cima_containers",
+        "Number of the containers tracked by the agent."
+    )
+    .expect("Failed to register metric.");
+    pub static ref EVENTLOG_ENTRIES: IntGauge = register_int_gauge!(
+        "cima_eventlog_entries",
+        "Number of the event log entries loaded by the agent."
+    )
+    .expect("Failed to register metric.");
+    pub static ref IMA_EVENTS: IntCounter = register_int_counter!(
+        "cima_ima_events_total",
+        "Number of the IMA events ingested by the agent."
+    )
+    .expect("Failed to register metric.");
+    pub static ref IMA_INGESTION_LAG: IntGauge = register_int_gauge!(
+        "cima_ima_ingestion_lag_events",
+        "Number of the IMA events measured by the kernel but not ingested yet."
+    )
+    .expect("Failed to register metric.");
+    pub static ref IMA_LAST_INGESTION: Gauge = register_gauge!(
+        "cima_ima_last_ingestion_timestamp_seconds",
+        "Unix time of the last successful event log ingestion."
+    )
+    .expect("Failed to register metric.");
+    pub static ref REPORT_DURATION: Histogram = register_histogram!(
+        "cima_report_duration_seconds",
+        "Latency of the CC report generation by the backend."
+    )
+    .expect("Failed to register metric.");
+    pub static ref REPORT_CACHE_HITS: IntCounter = register_int_counter!(
+        "cima_report_cache_hits_total",
+        "Number of the CC reports served from the cache."
+    )
+    .expect("Failed to register metric.");
+    pub static ref REPORT_CACHE_MISSES: IntCounter = register_int_counter!(
+        "cima_report_cache_misses_total",
+        "Number of the CC report requests not found in the cache."
+    )
+    .expect("Failed to register metric.");
+    pub static ref REPORT_CACHE_ENTRIES: IntGauge = register_int_gauge!(
+        "cima_report_cache_entries",
+        "Number of the CC reports in the cache."
+    )
+    .expect("Failed to register metric.");
+    pub static ref BACKEND_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "cima_backend_errors_total",
+        "Number of the errors returned by the TEE backend, by operation.",
+        &["operation"]
+    )
+    .expect("Failed to register metric.");
+}
+
+pub fn backend_error(operation: &str) {
+    BACKEND_ERRORS.with_label_values(&[operation]).inc();
+}
+
+pub fn ingested() {
+    if let Ok(v) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        IMA_LAST_INGESTION.set(v.as_secs_f64());
+    }
+}
+
+// The lag is taken when scraped, from the number of measurements in the kernel IMA list.
+fn update_ima_lag() {
+    let count = match read_to_string(IMA_COUNT_PATH) {
+        Ok(v) => match v.trim().parse::<i64>() {
+            Ok(v) => v,
+            Err(_) => return,
+        },
+        Err(_) => return,
+    };
+    IMA_INGESTION_LAG.set((count - IMA_EVENTS.get() as i64).max(0));
+}
+
+fn encode() -> Result<Vec<u8>, prometheus::Error> {
+    update_ima_lag();
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
+
+async fn handle(request: http::Request<Body>) -> Result<http::Response<Body>, Infallible> {
+    let mut response = http::Response::new(Body::empty());
+    if request.uri().path() != "/metrics" {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return Ok(response);
+    }
+
+    match encode() {
+        Ok(v) => {
+            response.headers_mut().insert(
+                http::header::CONTENT_TYPE,
+                http::HeaderValue::from_static(prometheus::TEXT_FORMAT),
+            );
+            *response.body_mut() = Body::from(v);
+        }
+        Err(e) => {
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            *response.body_mut() = Body::from(e.to_string());
+        }
+    }
+    Ok(response)
+}
+
+// Bind the metrics endpoint, the returned future serves "/metrics" on it.
+pub fn serve(
+    addr: SocketAddr,
+) -> Result<impl Future<Output = Result<(), hyper::Error>>, hyper::Error> {
+    let server = hyper::Server::try_bind(&addr)?.serve(make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(handle))
+    }));
+    info!("[cima-server]: serving metrics on {}", addr);
+    Ok(server)
+}
+
+// The method of a call of the Cima service, from its "/<service>/<method>" path.
+fn rpc_method(path: &str) -> Option<String> {
+    let (service, method) = path.strip_prefix('/')?.split_once('/')?;
+    if service != CimaServer::<Service>::NAME {
+        return None;
+    }
+    Some(method.to_string())
+}
+
+// Count and time the calls of the Cima service by method and status code.
+#[derive(Clone, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+}
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+impl<S, B, R> TowerService<http::Request<B>> for MetricsService<S>
+where
+    S: TowerService<http::Request<B>, Response = http::Response<R>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let method = rpc_method(request.uri().path());
+        let start = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await;
+            if let Some(method) = method {
+                // Errors are sent as trailers-only responses, with the status in the headers.
+                let code = match &response {
+                    Ok(v) => match v.headers().get("grpc-status") {
+                        Some(s) => Code::from_bytes(s.as_bytes()),
+                        None => Code::Ok,
+                    },
+                    Err(_) => Code::Unknown,
+                };
+                RPC_REQUESTS
+                    .with_label_values(&[&method, &format!("{:?}", code)])
+                    .inc();
+                RPC_DURATION
+                    .with_label_values(&[&method])
+                    .observe(start.elapsed().as_secs_f64());
+            }
+            response
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Error};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
@@ -14,11 +15,21 @@ struct KubernetesPolicy {
     pods: Option<Vec<String>>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ApplicationPolicy {
+    enabled: Option<bool>,
+    burst: Option<u32>,
+    rate_per_minute: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
 struct ContainerPolicy {
     with_parameter: Option<bool>,
     isolated: Option<bool>,
+    ima_unavailable: Option<String>,
+    application: Option<ApplicationPolicy>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
@@ -29,18 +40,64 @@ struct MeasurePolicy {
     container: Option<ContainerPolicy>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+struct VsockPolicy {
+    allowed_cids: Option<Vec<u32>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReportPolicy {
+    burst: Option<u32>,
+    rate_per_minute: Option<u32>,
+    max_concurrent: Option<u32>,
+    cache_ttl_seconds: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PolicyConfig {
     backend: Option<String>,
     hash_algorithm: Option<String>,
     measure: Option<MeasurePolicy>,
+    vsock: Option<VsockPolicy>,
+    report: Option<ReportPolicy>,
 }
 
 impl PolicyConfig {
-    pub fn new(path: String) -> PolicyConfig {
-        let file = std::fs::File::open(path).expect("Failed to open policy file.");
-        serde_yaml::from_reader(file).expect("Failed to serialize policy file.")
+    pub fn new(path: String) -> Result<PolicyConfig, Error> {
+        let file = std::fs::File::open(&path)
+            .map_err(|e| anyhow!("Failed to open policy file {}: {}", path, e))?;
+        serde_yaml::from_reader(file)
+            .map_err(|e| anyhow!("Failed to parse policy file {}: {}", path, e))
+    }
+
+    pub fn backend(&self) -> Option<&String> {
+        self.backend.as_ref()
+    }
+
+    pub fn vsock_allowed_cids(&self) -> Option<&Vec<u32>> {
+        match &self.vsock {
+            Some(v) => v.allowed_cids.as_ref(),
+            None => None,
+        }
+    }
+
+    pub fn report_burst(&self) -> Option<u32> {
+        self.report.as_ref().and_then(|v| v.burst)
+    }
+
+    pub fn report_rate(&self) -> Option<u32> {
+        self.report.as_ref().and_then(|v| v.rate_per_minute)
+    }
+
+    pub fn report_max_concurrent(&self) -> Option<u32> {
+        self.report.as_ref().and_then(|v| v.max_concurrent)
+    }
+
+    pub fn report_cache_ttl(&self) -> Option<u32> {
+        self.report.as_ref().and_then(|v| v.cache_ttl_seconds)
     }
 
     pub fn hash_alogrithm(&self) -> Option<&String> {
@@ -76,4 +133,36 @@ impl PolicyConfig {
             None => None,
         }
     }
+
+    pub fn container_ima_unavailable(&self) -> Option<&String> {
+        match &self.measure {
+            Some(v) => match &v.container {
+                Some(v) => v.ima_unavailable.as_ref(),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    fn container_application(&self) -> Option<&ApplicationPolicy> {
+        match &self.measure {
+            Some(v) => match &v.container {
+                Some(v) => v.application.as_ref(),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn container_application_enabled(&self) -> Option<bool> {
+        self.container_application().and_then(|v| v.enabled)
+    }
+
+    pub fn container_application_burst(&self) -> Option<u32> {
+        self.container_application().and_then(|v| v.burst)
+    }
+
+    pub fn container_application_rate(&self) -> Option<u32> {
+        self.container_application().and_then(|v| v.rate_per_minute)
+    }
 }
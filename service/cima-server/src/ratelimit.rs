@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tonic::{metadata::MetadataValue, Status};
+
+pub const RETRY_AFTER_KEY: &str = "retry-after-ms";
+// Retry hint when all the slots of a concurrency cap are taken.
+pub const BUSY_RETRY_AFTER: Duration = Duration::from_secs(1);
+// How often the idle buckets are looked for.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(burst: u32, per_minute: u32) -> TokenBucket {
+        TokenBucket {
+            capacity: burst.max(1) as f64,
+            rate: per_minute as f64 / 60.0,
+            tokens: burst.max(1) as f64,
+            last: Instant::now(),
+        }
+    }
+
+    fn refilled(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        (self.tokens + elapsed * self.rate).min(self.capacity)
+    }
+
+    // Whether the bucket is refilled, so it is the same as a new one.
+    pub fn full(&self, now: Instant) -> bool {
+        self.refilled(now) >= self.capacity
+    }
+
+    // Take a token, or return how long to wait until one is available.
+    pub fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.tokens = self.refilled(now);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if self.rate <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+}
+
+// Token buckets keyed by the caller, the idle ones are dropped once refilled.
+pub struct RateLimiter {
+    burst: u32,
+    per_minute: u32,
+    buckets: HashMap<String, TokenBucket>,
+    last_sweep: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, per_minute: u32) -> RateLimiter {
+        RateLimiter {
+            burst,
+            per_minute,
+            buckets: HashMap::new(),
+            last_sweep: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    // Drop the buckets refilled since they were last used.
+    pub fn sweep(&mut self) {
+        let now = Instant::now();
+        self.buckets.retain(|_, b| !b.full(now));
+        self.last_sweep = now;
+    }
+
+    pub fn take(&mut self, key: &str) -> Result<(), Duration> {
+        if self.last_sweep.elapsed() >= SWEEP_INTERVAL {
+            self.sweep();
+        }
+
+        let (burst, per_minute) = (self.burst, self.per_minute);
+        self.buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(burst, per_minute))
+            .take()
+    }
+}
+
+fn with_retry_hint(message: &str, retry_after: Duration) -> Status {
+    let millis = retry_after.as_millis().min(u64::MAX as u128) as u64;
+    let mut status = Status::resource_exhausted(format!("{}, retry after {} ms.", message, millis));
+    if let Ok(v) = MetadataValue::try_from(millis.to_string()) {
+        status.metadata_mut().insert(RETRY_AFTER_KEY, v);
+    }
+    status
+}
+
+// RESOURCE_EXHAUSTED status with the retry hint in the message and the metadata.
+pub fn exhausted(what: &str, retry_after: Duration) -> Status {
+    with_retry_hint(&format!("{} rate limit exceeded", what), retry_after)
+}
+
+// RESOURCE_EXHAUSTED status for a concurrency cap, with the retry hint.
+pub fn busy(what: &str) -> Status {
+    with_retry_hint(
+        &format!("Too many concurrent {} requests", what),
+        BUSY_RETRY_AFTER,
+    )
+}
@@ -0,0 +1,96 @@
+use anyhow::Error;
+use evidence_api::cc_type::TeeType;
+use openssl::hash::{hash, MessageDigest};
+use tonic::Status;
+
+/*
+ * Size rules of the nonce and user_data of a CC report request:
+ *  - Both must be base64 encoded and at most MAX_NONCE_SIZE/MAX_USER_DATA_SIZE bytes
+ *    once decoded, otherwise the request fails with INVALID_ARGUMENT.
+ *  - How much of them the backend takes is given per TEE type by report_data_size,
+ *    requests on any other TEE type fail with FAILED_PRECONDITION.
+ *  - On TDX the backend signs SHA512(nonce || user_data) as REPORTDATA, so both are
+ *    passed through unchanged.
+ *  - On the other TEEs, when the decoded nonce and user_data together exceed the
+ *    report data size of the TEE, the report is requested with the nonce
+ *    SHA512(nonce || user_data) and no user_data.
+ */
+pub const MAX_NONCE_SIZE: usize = 1024;
+pub const MAX_USER_DATA_SIZE: usize = 4096;
+
+#[derive(Debug, PartialEq)]
+pub enum ReportDataSize {
+    // The backend hashes the nonce and user_data itself, any size fits.
+    Hashed,
+    // The backend takes at most this many bytes of nonce and user_data.
+    Bytes(usize),
+}
+
+pub fn report_data_size(tee: &TeeType) -> Result<ReportDataSize, Error> {
+    match tee {
+        TeeType::TDX => Ok(ReportDataSize::Hashed),
+        // The SEV-SNP REPORT_DATA is 64 bytes.
+        TeeType::SEV => Ok(ReportDataSize::Bytes(64)),
+        // The CCA challenge is 64 bytes.
+        TeeType::CCA => Ok(ReportDataSize::Bytes(64)),
+        // The TPM2_Quote qualifying data is a TPM2B_DATA, at most sizeof(TPMT_HA) bytes.
+        TeeType::TPM => Ok(ReportDataSize::Bytes(66)),
+        _ => Err(Status::failed_precondition(format!(
+            "CC reports of TEE type {} are not supported.",
+            tee.clone() as i32
+        ))
+        .into()),
+    }
+}
+
+pub fn decode(name: &str, value: &Option<String>, max_size: usize) -> Result<Vec<u8>, Error> {
+    let data = match value {
+        Some(v) => match base64::decode(v) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(Status::invalid_argument(format!(
+                    "{} is not base64 encoded: {:?}",
+                    name, e
+                ))
+                .into())
+            }
+        },
+        None => vec![],
+    };
+
+    if data.len() > max_size {
+        return Err(Status::invalid_argument(format!(
+            "{} is {} bytes, larger than the maximum {} bytes.",
+            name,
+            data.len(),
+            max_size
+        ))
+        .into());
+    }
+
+    Ok(data)
+}
+
+pub fn fit(
+    tee: &TeeType,
+    nonce: Option<String>,
+    user_data: Option<String>,
+) -> Result<(Option<String>, Option<String>), Error> {
+    let size = report_data_size(tee)?;
+    let nonce_data = decode("nonce", &nonce, MAX_NONCE_SIZE)?;
+    let user_data_data = decode("user_data", &user_data, MAX_USER_DATA_SIZE)?;
+
+    match size {
+        ReportDataSize::Hashed => return Ok((nonce, user_data)),
+        ReportDataSize::Bytes(v) if nonce_data.len() + user_data_data.len() <= v => {
+            return Ok((nonce, user_data))
+        }
+        _ => {}
+    }
+
+    let digest = hash(
+        MessageDigest::sha512(),
+        &[nonce_data, user_data_data].concat(),
+    )?;
+    Ok((Some(base64::encode(digest)), None))
+}
@@ -1,31 +1,228 @@
-use anyhow::Result;
+use anyhow::{anyhow, Error, Result};
 use lazy_static::lazy_static;
-use std::sync::Mutex;
-use tonic::{Request, Response, Status};
+use log::{info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tonic::{transport::server::UdsConnectInfo, Request, Response, Status};
+use tonic_health::{server::HealthReporter, ServingStatus};
 
 use crate::{
-    agent::Agent,
+    agent::{Agent, Scope},
+    audit::{AuditLog, AuditRecord, Caller},
+    cel,
     cima_pb::{
-        cima_server::Cima, GetCcEventlogRequest, GetCcEventlogResponse, GetCcMeasurementRequest,
+        cima_server::{Cima, CimaServer},
+        EventlogFormat, ExtendContainerMeasurementRequest, ExtendContainerMeasurementResponse,
+        GetCcEventlogRequest, GetCcEventlogResponse, GetCcMeasurementRequest,
         GetCcMeasurementResponse, GetCcReportRequest, GetCcReportResponse,
-        GetDefaultAlgorithmRequest, GetDefaultAlgorithmResponse, GetMeasurementCountRequest,
-        GetMeasurementCountResponse,
+        GetDefaultAlgorithmRequest, GetDefaultAlgorithmResponse, GetEvidenceRequest,
+        GetEvidenceResponse, GetIsolationModeRequest, GetIsolationModeResponse,
+        GetMeasurementCountRequest, GetMeasurementCountResponse, GetReadinessRequest,
+        GetReadinessResponse, GetServerInfoRequest, GetServerInfoResponse,
     },
+    container::Container,
+    identity::{cert_subjects, IdentityMap},
+    ima,
     policy::PolicyConfig,
+    ratelimit,
+    vsock::{self, VsockConnectInfo},
 };
 
 lazy_static! {
     static ref AGENT: Mutex<Agent> = Mutex::new(Agent::new());
 }
 
-pub struct Service;
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// Errors carrying a gRPC status keep their code, the others are reported as internal errors.
+fn to_status(e: Error) -> Status {
+    match e.downcast::<Status>() {
+        Ok(s) => s,
+        Err(e) => Status::internal(e.to_string()),
+    }
+}
+
+pub struct Service {
+    identities: IdentityMap,
+    vsock_cids: Vec<u32>,
+    audit: Option<AuditLog>,
+    report_permits: Option<Arc<Semaphore>>,
+}
+
 impl Service {
     pub fn new(policy: PolicyConfig) -> Service {
+        let vsock_cids = policy.vsock_allowed_cids().cloned().unwrap_or_default();
+        let report_permits = policy
+            .report_max_concurrent()
+            .map(|v| Arc::new(Semaphore::new(v.max(1) as usize)));
         match AGENT.lock().expect("Agent lock() failed.").init(policy) {
             Err(e) => panic!("Server panic {:?}", e),
             Ok(_v) => _v,
         }
-        Service {}
+        Service {
+            identities: IdentityMap::default(),
+            vsock_cids,
+            audit: None,
+            report_permits,
+        }
+    }
+
+    pub fn with_identities(mut self, identities: IdentityMap) -> Service {
+        self.identities = identities;
+        self
+    }
+
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Service {
+        self.audit = Some(audit);
+        self
+    }
+
+    /*
+     * Generate a report on the blocking threads, so the requests waiting for the agent do
+     * not hold the tokio workers. When the concurrency is capped by the policy, a slot is
+     * taken first and held until the backend returns, which bounds the report requests
+     * waiting for the agent and calling the backend.
+     */
+    async fn blocking_report<R, F>(&self, generate: F) -> Result<R, Error>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Agent) -> Result<R, Error> + Send + 'static,
+    {
+        let permit = match &self.report_permits {
+            Some(v) => match v.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return Err(ratelimit::busy("report").into()),
+            },
+            None => None,
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            generate(&mut AGENT.lock().expect("Agent lock() failed."))
+        })
+        .await;
+        match result {
+            Ok(v) => v,
+            Err(e) => Err(anyhow!("Report generation failed: {}", e)),
+        }
+    }
+
+    // Record the result of an evidence request in the audit log.
+    fn audit<T, R>(
+        &self,
+        method: &'static str,
+        request: &Request<T>,
+        container_id: &str,
+        nonce: Option<&String>,
+        result: &Result<R, Error>,
+    ) {
+        if let Some(audit) = &self.audit {
+            audit.record(&AuditRecord::new(
+                method,
+                Caller::of(request),
+                container_id,
+                nonce,
+                result,
+            ));
+        }
+    }
+
+    /*
+     * The scope of the caller. Local callers get the container they ask for, the container
+     * isolation applies to them. TLS clients are checked against the identity mapping of
+     * their certificate. vsock callers are outside the VM, they only get the system scope.
+     */
+    fn scope<T>(&self, request: &Request<T>, container_id: Option<&str>) -> Result<Scope, Error> {
+        if let Some(info) = request.extensions().get::<VsockConnectInfo>() {
+            return self.vsock_scope(info, container_id);
+        }
+
+        let certs = match request.peer_certs() {
+            Some(v) => v,
+            None => {
+                return Ok(Scope::Container(
+                    container_id.unwrap_or_default().to_string(),
+                ))
+            }
+        };
+
+        let subjects = match certs.first() {
+            Some(v) => match cert_subjects(v.get_ref()) {
+                Ok(v) => v,
+                Err(e) => return Err(Status::unauthenticated(e.to_string()).into()),
+            },
+            None => {
+                return Err(Status::unauthenticated("No client certificate is provided.").into())
+            }
+        };
+
+        self.identities.authorize(&subjects, container_id)
+    }
+
+    fn vsock_scope(
+        &self,
+        info: &VsockConnectInfo,
+        container_id: Option<&str>,
+    ) -> Result<Scope, Error> {
+        vsock::check_peer(&self.vsock_cids, info)?;
+
+        match container_id {
+            Some(v) if !v.is_empty() => Err(Status::permission_denied(
+                "vsock callers can only query the system evidence.",
+            )
+            .into()),
+            _ => Ok(Scope::System),
+        }
+    }
+}
+
+// Ingest the event logs periodically, so the requests only fetch the few recent ones.
+pub async fn watch_ingestion(period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = AGENT.lock().expect("Agent lock() failed.").ingest() {
+            warn!("[cima-server]: failed to ingest the event logs: {:?}", e);
+        }
+    }
+}
+
+// Keep the health status of the Cima service, and of the server, in line with the readiness.
+pub async fn watch_health(mut reporter: HealthReporter, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    let mut last_ready = None;
+    loop {
+        interval.tick().await;
+        let measurements = Agent::measured_imrs();
+        let readiness = AGENT
+            .lock()
+            .expect("Agent lock() failed.")
+            .get_readiness(measurements);
+        if last_ready == Some(readiness.ready) {
+            continue;
+        }
+        last_ready = Some(readiness.ready);
+
+        let status = if readiness.ready {
+            info!("[cima-server]: the agent is ready.");
+            ServingStatus::Serving
+        } else {
+            for check in readiness.checks.iter().filter(|c| !c.ok) {
+                warn!(
+                    "[cima-server]: {} check fails: {}",
+                    check.name, check.reason
+                );
+            }
+            ServingStatus::NotServing
+        };
+        reporter
+            .set_service_status(
+                <CimaServer<Service> as tonic::server::NamedService>::NAME,
+                status,
+            )
+            .await;
+        reporter.set_service_status("", status).await;
     }
 }
 
@@ -33,15 +230,16 @@ impl Service {
 impl Cima for Service {
     async fn get_default_algorithm(
         &self,
-        _request: Request<GetDefaultAlgorithmRequest>,
+        request: Request<GetDefaultAlgorithmRequest>,
     ) -> Result<Response<GetDefaultAlgorithmResponse>, Status> {
+        self.scope(&request, None).map_err(to_status)?;
         let algo_id = match AGENT
             .lock()
             .expect("Agent lock() failed.")
             .get_default_algorithm()
         {
             Ok(v) => v,
-            Err(e) => return Err(Status::internal(e.to_string())),
+            Err(e) => return Err(to_status(e)),
         };
 
         Ok(Response::new(GetDefaultAlgorithmResponse { algo_id }))
@@ -49,15 +247,16 @@ impl Cima for Service {
 
     async fn get_measurement_count(
         &self,
-        _request: Request<GetMeasurementCountRequest>,
+        request: Request<GetMeasurementCountRequest>,
     ) -> Result<Response<GetMeasurementCountResponse>, Status> {
+        self.scope(&request, None).map_err(to_status)?;
         let count = match AGENT
             .lock()
             .expect("Agent lock() failed.")
             .get_measurement_count()
         {
             Ok(v) => v,
-            Err(e) => return Err(Status::internal(e.to_string())),
+            Err(e) => return Err(to_status(e)),
         };
 
         Ok(Response::new(GetMeasurementCountResponse { count }))
@@ -67,52 +266,207 @@ impl Cima for Service {
         &self,
         request: Request<GetCcMeasurementRequest>,
     ) -> Result<Response<GetCcMeasurementResponse>, Status> {
-        let req = request.into_inner();
-        let measurement = match AGENT
-            .lock()
-            .expect("Agent lock() failed.")
-            .get_cc_measurement(req.container_id, req.index, req.algo_id)
-        {
-            Ok(v) => v,
-            Err(e) => return Err(Status::internal(e.to_string())),
-        };
+        let req = request.get_ref();
+        let result = self
+            .scope(&request, Some(&req.container_id))
+            .and_then(|scope| {
+                let mut agent = AGENT.lock().expect("Agent lock() failed.");
+                let measurement =
+                    agent.get_cc_measurement(scope.clone(), req.index, req.algo_id)?;
+                Ok(GetCcMeasurementResponse {
+                    measurement: Some(measurement),
+                    unfiltered: agent.unfiltered(&scope),
+                })
+            });
+        self.audit(
+            "GetCcMeasurement",
+            &request,
+            &req.container_id,
+            None,
+            &result,
+        );
 
-        Ok(Response::new(GetCcMeasurementResponse {
-            measurement: Some(measurement),
-        }))
+        result.map(Response::new).map_err(to_status)
     }
 
     async fn get_cc_eventlog(
         &self,
         request: Request<GetCcEventlogRequest>,
     ) -> Result<Response<GetCcEventlogResponse>, Status> {
-        let req = request.into_inner();
-        let event_logs = match AGENT.lock().expect("Agent lock() failed.").get_cc_eventlog(
-            req.container_id,
-            req.start,
-            req.count,
-        ) {
-            Ok(v) => v,
-            Err(e) => return Err(Status::internal(e.to_string())),
-        };
+        let req = request.get_ref();
+        let result = self
+            .scope(&request, Some(&req.container_id))
+            .and_then(|scope| {
+                let start = req.start.unwrap_or(0);
+                let mut agent = AGENT.lock().expect("Agent lock() failed.");
+                let event_logs = agent.get_cc_eventlog(scope.clone(), req.start, req.count)?;
+
+                let mut response = GetCcEventlogResponse {
+                    unfiltered: agent.unfiltered(&scope),
+                    ..Default::default()
+                };
+                match req.format() {
+                    EventlogFormat::Tcg => response.event_logs = event_logs,
+                    EventlogFormat::CelJson => {
+                        response.cel_event_logs = cel::to_json(&event_logs, start)?
+                    }
+                    EventlogFormat::CelCbor => {
+                        response.cel_event_logs = cel::to_cbor(&event_logs, start)?
+                    }
+                    EventlogFormat::ImaAscii => {
+                        response.ima_ascii_event_logs = ima::ascii_runtime_measurements(&event_logs)
+                    }
+                }
+                Ok(response)
+            });
+        self.audit("GetCcEventlog", &request, &req.container_id, None, &result);
 
-        Ok(Response::new(GetCcEventlogResponse { event_logs }))
+        result.map(Response::new).map_err(to_status)
     }
 
     async fn get_cc_report(
         &self,
         request: Request<GetCcReportRequest>,
     ) -> Result<Response<GetCcReportResponse>, Status> {
+        let req = request.get_ref();
+        let (nonce, user_data) = (req.nonce.clone(), req.user_data.clone());
+        let result = match self
+            .scope(&request, Some(&req.container_id))
+            .and_then(|scope| Ok((scope, Caller::of(&request).identity()?)))
+        {
+            Ok((scope, caller)) => {
+                self.blocking_report(move |agent| {
+                    agent.get_cc_report(scope, &caller, nonce, user_data)
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        self.audit(
+            "GetCcReport",
+            &request,
+            &req.container_id,
+            req.nonce.as_ref(),
+            &result,
+        );
+
+        result.map(Response::new).map_err(to_status)
+    }
+
+    async fn get_isolation_mode(
+        &self,
+        request: Request<GetIsolationModeRequest>,
+    ) -> Result<Response<GetIsolationModeResponse>, Status> {
+        self.scope(&request, None).map_err(to_status)?;
+        let (mode, ima_enabled) = AGENT
+            .lock()
+            .expect("Agent lock() failed.")
+            .get_isolation_mode();
+
+        Ok(Response::new(GetIsolationModeResponse {
+            mode: mode.into(),
+            ima_enabled,
+        }))
+    }
+
+    async fn get_server_info(
+        &self,
+        request: Request<GetServerInfoRequest>,
+    ) -> Result<Response<GetServerInfoResponse>, Status> {
+        let scope = self.scope(&request, None).map_err(to_status)?;
+        let info = match AGENT
+            .lock()
+            .expect("Agent lock() failed.")
+            .get_server_info(scope)
+        {
+            Ok(v) => v,
+            Err(e) => return Err(to_status(e)),
+        };
+
+        Ok(Response::new(info))
+    }
+
+    async fn get_evidence(
+        &self,
+        request: Request<GetEvidenceRequest>,
+    ) -> Result<Response<GetEvidenceResponse>, Status> {
+        let req = request.get_ref();
+        let (nonce, user_data) = (req.nonce.clone(), req.user_data.clone());
+        let result = match self
+            .scope(&request, Some(&req.container_id))
+            .and_then(|scope| Ok((scope, Caller::of(&request).identity()?)))
+        {
+            Ok((scope, caller)) => {
+                self.blocking_report(move |agent| {
+                    agent.get_evidence(scope, &caller, nonce, user_data)
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        self.audit(
+            "GetEvidence",
+            &request,
+            &req.container_id,
+            req.nonce.as_ref(),
+            &result,
+        );
+
+        result.map(Response::new).map_err(to_status)
+    }
+
+    async fn extend_container_measurement(
+        &self,
+        request: Request<ExtendContainerMeasurementRequest>,
+    ) -> Result<Response<ExtendContainerMeasurementResponse>, Status> {
+        // The caller can only extend the measurement of the container it runs in.
+        let pid = match request.extensions().get::<UdsConnectInfo>() {
+            Some(v) => v.peer_cred.and_then(|c| c.pid()),
+            None => None,
+        };
+        let pid = match pid {
+            Some(v) => v,
+            None => {
+                return Err(Status::permission_denied(
+                    "Cannot identify the caller process.",
+                ))
+            }
+        };
+        let caller_id = match Container::id_of_process(pid) {
+            Ok(v) => v,
+            Err(e) => return Err(Status::permission_denied(e.to_string())),
+        };
+
         let req = request.into_inner();
-        let (cc_report, cc_type) = match AGENT.lock().expect("Agent lock() failed.").get_cc_report(
-            req.container_id,
-            req.nonce,
-            req.user_data,
-        ) {
+        if caller_id != req.container_id {
+            return Err(Status::permission_denied(
+                "The container ID does not belong to the caller.",
+            ));
+        }
+
+        let response = match AGENT
+            .lock()
+            .expect("Agent lock() failed.")
+            .extend_container_measurement(req.container_id, req.name, req.event, Some(pid))
+        {
             Ok(v) => v,
-            Err(e) => return Err(Status::internal(e.to_string())),
+            Err(e) => return Err(to_status(e)),
         };
 
-        Ok(Response::new(GetCcReportResponse { cc_report, cc_type }))
+        Ok(Response::new(response))
+    }
+
+    async fn get_readiness(
+        &self,
+        request: Request<GetReadinessRequest>,
+    ) -> Result<Response<GetReadinessResponse>, Status> {
+        self.scope(&request, None).map_err(to_status)?;
+        let measurements = Agent::measured_imrs();
+        let readiness = AGENT
+            .lock()
+            .expect("Agent lock() failed.")
+            .get_readiness(measurements);
+
+        Ok(Response::new(readiness))
     }
 }
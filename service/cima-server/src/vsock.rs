@@ -0,0 +1,166 @@
+use anyhow::Error;
+use cima_common::vsock;
+use log::warn;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{sleep, Sleep};
+use tokio_stream::Stream;
+use tonic::{transport::server::Connected, Status};
+
+// How long to wait before accepting again when the process is out of file descriptors or memory.
+pub const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+// Address of the vsock peer, available in the request extensions of vsock callers.
+#[derive(Clone, Debug)]
+pub struct VsockConnectInfo {
+    pub peer_cid: u32,
+    pub peer_port: u32,
+}
+
+// Only the CIDs listed in the policy are allowed, none when the list is not set.
+pub fn check_peer(allowed_cids: &[u32], info: &VsockConnectInfo) -> Result<(), Error> {
+    if !allowed_cids.contains(&info.peer_cid) {
+        return Err(Status::permission_denied(format!(
+            "The vsock CID {} is not allowed according to the policy.",
+            info.peer_cid
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+// The vsock listener of the server, the socket is shared with the SDK in cima-common.
+pub struct VsockListener {
+    inner: vsock::VsockListener,
+}
+
+impl VsockListener {
+    // Listen on the port for any CID.
+    pub fn bind(port: u32) -> io::Result<VsockListener> {
+        Ok(VsockListener {
+            inner: vsock::VsockListener::bind(port)?,
+        })
+    }
+
+    pub fn incoming(self) -> VsockIncoming<VsockListener> {
+        VsockIncoming::new(self)
+    }
+}
+
+// A listener the connections are accepted from.
+pub trait Accept {
+    type Conn;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<Self::Conn>>;
+}
+
+impl Accept for VsockListener {
+    type Conn = VsockStream;
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<VsockStream>> {
+        self.inner.poll_accept(cx).map_ok(VsockStream)
+    }
+}
+
+// Errors of the connection being accepted, the next one can be accepted right away.
+fn connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+    ) || e.raw_os_error() == Some(libc::EPROTO)
+}
+
+/*
+ * The accepted connections, which never ends with an error: a failed accept would stop the
+ * server. The errors are logged, and accepting is paused for a while on the errors of the
+ * process, like EMFILE, until some resources are released.
+ */
+pub struct VsockIncoming<L> {
+    listener: L,
+    backoff: Option<Pin<Box<Sleep>>>,
+}
+
+impl<L> VsockIncoming<L> {
+    pub fn new(listener: L) -> VsockIncoming<L> {
+        VsockIncoming {
+            listener,
+            backoff: None,
+        }
+    }
+}
+
+impl<L: Accept + Unpin> Stream for VsockIncoming<L> {
+    type Item = io::Result<L::Conn>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(backoff) = self.backoff.as_mut() {
+                ready!(backoff.as_mut().poll(cx));
+                self.backoff = None;
+            }
+
+            match ready!(self.listener.poll_accept(cx)) {
+                Ok(conn) => return Poll::Ready(Some(Ok(conn))),
+                Err(e) if connection_error(&e) => {
+                    warn!("[cima-server]: failed to accept a vsock connection: {}", e)
+                }
+                Err(e) => {
+                    warn!(
+                        "[cima-server]: failed to accept vsock connections, retry in {:?}: {}",
+                        ACCEPT_BACKOFF, e
+                    );
+                    self.backoff = Some(Box::pin(sleep(ACCEPT_BACKOFF)));
+                }
+            }
+        }
+    }
+}
+
+// A vsock connection, its peer address is the connect info of the requests.
+pub struct VsockStream(vsock::VsockStream);
+
+impl Connected for VsockStream {
+    type ConnectInfo = VsockConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        let (peer_cid, peer_port) = self.0.peer_addr();
+        VsockConnectInfo {
+            peer_cid,
+            peer_port,
+        }
+    }
+}
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}